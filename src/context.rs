@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use crate::install::{PackageManager, Preview};
+use crate::log::Logger;
+use crate::privilege::Elevator;
+use crate::report::Reporter;
+
+/// Runtime options resolved from the command line, passed to every command
+/// and install handler.
+pub struct Context {
+    pub log: Logger,
+    /// Per-entry events and the summary of the running command (`--output`).
+    pub report: Reporter,
+    /// Proceed with all warnings and skippings (`--force`).
+    pub force: bool,
+    /// Stop at the first failed entry instead of continuing with the rest (`--fail-fast`).
+    pub fail_fast: bool,
+    /// Answer yes to confirmation prompts (`--yes`).
+    pub assume_yes: bool,
+    /// Program running commands as root (`--elevate`, detected when omitted).
+    pub elevator: Elevator,
+    /// Directory containing the asset folders (`--assets-dir` or `HYDE_EXT_ASSETS`).
+    pub assets_dir: PathBuf,
+    /// Package selection inside package list groups (`install --groups/--packages`).
+    pub packages: PackageOptions,
+    /// Rule selection of the firewall handler (`install --firewall-rules`, `firewall switch`).
+    pub firewall: FirewallOptions,
+    /// Logo of the fastfetch configuration (`install --fastfetch-logo`).
+    pub fastfetch: FastfetchOptions,
+}
+
+/// Second-level selection for the packages handler. When both lists are
+/// empty the groups and packages are picked interactively, or every default
+/// package is installed when stdin is not a terminal.
+#[derive(Debug, Default)]
+pub struct PackageOptions {
+    /// Group names whose default packages are installed (case-insensitive).
+    pub groups: Vec<String>,
+    /// Individual packages, including commented out suggestions.
+    pub names: Vec<String>,
+    /// Backend forced with `--package-manager`; detected when `None`.
+    pub manager: Option<PackageManager>,
+}
+
+impl PackageOptions {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.names.is_empty()
+    }
+}
+
+/// Rule selection for the firewall handler. When neither rules nor a profile
+/// are given the rules are picked interactively, or the default rules are
+/// applied when stdin is not a terminal.
+#[derive(Debug, Default)]
+pub struct FirewallOptions {
+    /// Names of the `[[rule]]` tables of `UFW.toml` to apply (case-insensitive).
+    pub rules: Vec<String>,
+    /// `[[profile]]` of `UFW.toml` to switch to (`firewall switch`).
+    pub profile: Option<String>,
+}
+
+/// Logo choice for the fastfetch handler. When `logo` is `None` the image is
+/// picked interactively, or the `logo` of the catalog is used when stdin is
+/// not a terminal.
+#[derive(Debug, Default)]
+pub struct FastfetchOptions {
+    /// Image file name without extension, or `random` (case-insensitive).
+    pub logo: Option<String>,
+    /// How images are previewed in the picker, detected when `None`.
+    pub preview: Option<Preview>,
+}
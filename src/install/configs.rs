@@ -1,135 +1,135 @@
-use colored::*;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::fs;
 
-use crate::install::FileEntry;
-
-pub fn install(choice: &FileEntry) {
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "true";
+use crate::context::Context;
+use crate::install::{FileEntry, InstallError, Outcome};
+use crate::state::Record;
 
+pub(crate) fn install(ctx: &Context, choice: &FileEntry) -> Result<Outcome, InstallError> {
     let source_path = match &choice.source_path {
         Some(path) => path,
-        None => {
-            eprintln!("{} Source path for config is missing.", ":: Error:".red());
-            return;
-        }
+        None => return Err(InstallError::MissingPath("Source path for config is missing.".to_string())),
     };
 
     let target_path = match &choice.target_path {
         Some(path) => path,
-        None => {
-            eprintln!("{} Target path for config is missing.", ":: Error:".red());
-            return;
-        }
+        None => return Err(InstallError::MissingPath("Target path for config is missing.".to_string())),
     };
 
     let config_contents = match fs::read_to_string(source_path) {
         Ok(contents) => {
-            if debug_mode {
-                println!("{} Read contents from source: {}", ":: Debug:".blue(), source_path);
-            }
+            ctx.log.debug(format!("Read contents from source: {}", source_path));
             contents
         },
         Err(e) => {
-            eprintln!(
-                "{} Failed to read source config file {}: {}",
-                ":: Error:".red(),
-                source_path,
-                e
-            );
-            return;
+            return Err(InstallError::io(format!("Failed to read source config file {}", source_path), e));
         }
     };
 
-    let force_install = std::env::var("FORCE").unwrap_or_default() == "true";
-    if !force_install {
+    if !ctx.force {
         let target_file_contents = match fs::read_to_string(target_path) {
             Ok(contents) => {
-                if debug_mode {
-                    println!("{} Read contents from target: {}", ":: Debug:".blue(), target_path);
-                }
+                ctx.log.debug(format!("Read contents from target: {}", target_path));
                 contents
             },
             Err(_) => String::new(), // If the file doesn't exist or can't be read, treat as empty
         };
 
         if target_file_contents.contains("# ================== Customized Configurations Below ===========================") {
-            println!("{} {}", ":: Skipping".yellow(), choice.title);
-            return;
+            ctx.log.skip(&choice.title);
+            return Ok(Outcome::Skipped("Target already contains customized configurations.".to_string()));
         }
     }
 
-    println!("{} {}", ":: Installing".blue(), choice.title);
+    ctx.log.action("Installing", &choice.title);
 
     let mut target_file = match OpenOptions::new().append(true).open(target_path) {
         Ok(file) => {
-            if debug_mode {
-                println!("{} Opened target file for appending: {}", ":: Debug:".blue(), target_path);
-            }
+            ctx.log.debug(format!("Opened target file for appending: {}", target_path));
             file
         },
         Err(e) => {
-            eprintln!(
-                "{} Failed to open target config file {}: {}",
-                ":: Error:".red(),
-                target_path,
-                e
-            );
-            return;
+            return Err(InstallError::io(format!("Failed to open target config file {}", target_path), e));
         }
     };
 
     if let Err(e) = writeln!(target_file, "\n{}", config_contents) {
-        eprintln!(
-            "{} Failed to write to config file {}: {}",
-            ":: Error:".red(),
-            target_path,
-            e
-        );
-        return;
+        return Err(InstallError::io(format!("Failed to write to config file {}", target_path), e));
+    }
+
+    let mut record = Record {
+        target_path: Some(target_path.clone()),
+        appended: Some(format!("\n{}\n", config_contents)),
+        ..Record::default()
+    };
+    if target_path.contains("zshrc") {
+        match modify_zshrc(ctx, target_path) {
+            Ok(commented) => record.commented = commented,
+            Err(e) => return Err(InstallError::Partial { failed: vec![e.to_string()], record: Some(Box::new(record)) }),
+        }
     }
 
-    println!(
-        "{} installed in {}",
-        "  -> Successfully".green(),
-        target_path
-    );
+    ctx.log.success(format!("installed in {}", target_path));
+    Ok(Outcome::Applied(Box::new(record)))
 }
 
-pub fn modify_zshrc(choice: &FileEntry) {
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "true";
+/// Removes the content appended by `install` from the target file and
+/// uncomments the lines it commented out.
+pub(crate) fn uninstall(ctx: &Context, record: &Record) -> Result<Outcome, InstallError> {
+    let (target_path, appended) = match (&record.target_path, &record.appended) {
+        (Some(target_path), Some(appended)) => (target_path, appended),
+        _ => return Err(InstallError::MissingPath("No appended configuration was recorded.".to_string())),
+    };
 
-    let target = match &choice.target_path {
-        Some(path) => path,
+    let contents = fs::read_to_string(target_path)
+        .map_err(|e| InstallError::io(format!("Failed to read config file {}", target_path), e))?;
+
+    let position = match contents.rfind(appended.as_str()) {
+        Some(position) => position,
         None => {
-            eprintln!("{} Target path for zshrc is missing.", ":: Error:".red());
-            return;
+            return Err(InstallError::Conflict(format!(
+                "The installed configuration in {} was modified or removed.",
+                target_path
+            )))
         }
     };
 
-    let contents = match fs::read_to_string(target) {
-        Ok(contents) => {
-            if debug_mode {
-                println!("{} Read zshrc contents from: {}", ":: Debug:".blue(), target);
-            }
-            contents
-        },
-        Err(e) => {
-            eprintln!(
-                "{} Failed to read source zshrc file {}: {}",
-                ":: Error:".red(),
-                target,
-                e
-            );
-            return;
-        }
-    };
+    let mut restored = contents;
+    restored.replace_range(position..position + appended.len(), "");
+    if !record.commented.is_empty() {
+        restored = restore_commented(&restored, &record.commented);
+    }
+    fs::write(target_path, restored)
+        .map_err(|e| InstallError::io(format!("Failed to update config file {}", target_path), e))?;
+
+    ctx.log.success(format!("removed configuration from {}", target_path));
+    Ok(Outcome::Applied(Box::new(record.clone())))
+}
+
+/// Whether the content appended by `install` is still present in the target file.
+pub(crate) fn is_present(record: &Record) -> bool {
+    match (&record.target_path, &record.appended) {
+        (Some(target_path), Some(appended)) => fs::read_to_string(target_path)
+            .map(|contents| contents.contains(appended.as_str()))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
 
-    let modified_contents = contents
+/// Comments out the lines starting `pokemon-colorscripts` in `target` and
+/// returns them, so that uninstall can restore them.
+fn modify_zshrc(ctx: &Context, target: &str) -> Result<Vec<String>, InstallError> {
+    let contents = fs::read_to_string(target)
+        .map_err(|e| InstallError::io(format!("Failed to read target zshrc file {}", target), e))?;
+    ctx.log.debug(format!("Read zshrc contents from: {}", target));
+
+    let mut commented = Vec::new();
+    let mut modified_contents = contents
         .lines()
         .map(|line| {
             if line.starts_with("pokemon-colorscripts") && !line.trim_start().starts_with('#') {
+                commented.push(line.to_string());
                 format!("#{}", line)
             } else {
                 line.to_string()
@@ -137,20 +137,32 @@ pub fn modify_zshrc(choice: &FileEntry) {
         })
         .collect::<Vec<String>>()
         .join("\n");
-
-    if let Err(e) = fs::write(target, modified_contents) {
-        eprintln!(
-            "{} Failed to update target zshrc file {}: {}",
-            ":: Error:".red(),
-            target,
-            e
-        );
-        return;
+    if commented.is_empty() {
+        return Ok(commented);
+    }
+    if contents.ends_with('\n') {
+        modified_contents.push('\n');
     }
 
-    println!(
-        "{} {}",
-        "  ->".blue(),
-        "pokemon-colorscripts disabled on terminal startup".yellow()
-    );
+    fs::write(target, modified_contents)
+        .map_err(|e| InstallError::io(format!("Failed to update target zshrc file {}", target), e))?;
+
+    ctx.log.detail("pokemon-colorscripts disabled on terminal startup");
+    Ok(commented)
+}
+
+/// Removes the `#` that `modify_zshrc` put before each of the `commented`
+/// lines still found in `contents`.
+fn restore_commented(contents: &str, commented: &[String]) -> String {
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    for line in commented {
+        if let Some(index) = lines.iter().position(|candidate| *candidate == format!("#{}", line)) {
+            lines[index] = line.clone();
+        }
+    }
+    let mut restored = lines.join("\n");
+    if contents.ends_with('\n') {
+        restored.push('\n');
+    }
+    restored
 }
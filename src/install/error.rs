@@ -0,0 +1,113 @@
+use std::fmt;
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use crate::state::Record;
+
+/// Errors returned by install and uninstall handlers.
+#[derive(Debug)]
+pub(crate) enum InstallError {
+    /// The catalog entry is missing a `source_path` or `target_path`.
+    MissingPath(String),
+    /// A file system operation failed.
+    Io { message: String, source: io::Error },
+    /// An external command could not be started or exited with an error.
+    Command { command: String, message: String },
+    /// Some parts of the entry (e.g. package groups) failed, the rest was
+    /// applied and is described by `record`, which is kept in the install state.
+    Partial { failed: Vec<String>, record: Option<Box<Record>> },
+    /// The target was changed in a way the handler cannot safely handle.
+    Conflict(String),
+    /// An asset file (e.g. a package manifest) could not be parsed.
+    Parse(String),
+    /// The requested selection does not match anything in the entry.
+    Selection(String),
+}
+
+impl InstallError {
+    pub(crate) fn io(message: impl Into<String>, source: io::Error) -> InstallError {
+        InstallError::Io { message: message.into(), source }
+    }
+
+    pub(crate) fn command(command: impl Into<String>, message: impl Into<String>) -> InstallError {
+        InstallError::Command { command: command.into(), message: message.into() }
+    }
+
+    /// `command` could not be started.
+    pub(crate) fn not_started(command: impl Into<String>, error: io::Error) -> InstallError {
+        InstallError::command(command, format!("could not be started: {}", error))
+    }
+
+    /// `command` exited with `status`; its trimmed `stderr` is part of the
+    /// message when there is any.
+    pub(crate) fn failed(command: impl Into<String>, status: ExitStatus, stderr: &str) -> InstallError {
+        match stderr.trim() {
+            "" => InstallError::command(command, format!("failed with {}", status)),
+            stderr => InstallError::command(command, format!("failed with {}: {}", status, stderr)),
+        }
+    }
+
+    /// Short machine readable category, used for `error_kind` in reports.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            InstallError::MissingPath(_) => "missing_path",
+            InstallError::Io { .. } => "io",
+            InstallError::Command { .. } => "command",
+            InstallError::Partial { .. } => "partial",
+            InstallError::Conflict(_) => "conflict",
+            InstallError::Parse(_) => "parse",
+            InstallError::Selection(_) => "selection",
+        }
+    }
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallError::MissingPath(message) => write!(f, "{}", message),
+            InstallError::Io { message, source } => write!(f, "{}: {}", message, source),
+            InstallError::Command { command, message } => write!(f, "`{}` {}", command, message),
+            InstallError::Partial { failed, .. } => write!(f, "Failed: {}", failed.join(", ")),
+            InstallError::Conflict(message) | InstallError::Parse(message) | InstallError::Selection(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstallError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstallError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// `program args...` of `command`, as it is shown in errors and the run log.
+pub(crate) fn describe(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs `command` with the output of the terminal and waits for it.
+pub(crate) fn run_command(command: &mut Command) -> Result<(), InstallError> {
+    let status = command.status().map_err(|e| InstallError::not_started(describe(command), e))?;
+    if !status.success() {
+        return Err(InstallError::failed(describe(command), status, ""));
+    }
+    Ok(())
+}
+
+/// Runs `command` and returns its stdout. Fails when it cannot be started or
+/// exits with an error, with its stderr in the error.
+pub(crate) fn command_output(command: &mut Command) -> Result<String, InstallError> {
+    let output = command.output().map_err(|e| InstallError::not_started(describe(command), e))?;
+    if !output.status.success() {
+        return Err(InstallError::failed(describe(command), output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,417 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Select};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::context::Context;
+use crate::install::{expand_home, manager, remove_files, FileEntry, InstallError, Outcome};
+use crate::state::Record;
+
+use super::jsonc;
+use super::logo::{Art, Normalize};
+use super::preview::{self, Preview};
+
+/// Value of `--fastfetch-logo` and `logo` choosing a different image per shell start.
+pub(crate) const RANDOM: &str = "random";
+
+/// Image file extensions offered in the picker; every image is converted to PNG.
+const EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+const SCHEMA: &str = "https://github.com/fastfetch-cli/fastfetch/raw/dev/doc/json_schema.json";
+
+/// The `[fastfetch]` table of `FastFetchAssets.toml`.
+#[derive(Debug, Default, Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    fastfetch: Settings,
+}
+
+/// Logo and module settings written to `config.jsonc`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Settings {
+    /// Image used when stdin is not a terminal: a file name without extension, or `random`.
+    #[serde(default = "random")]
+    pub logo: String,
+    /// Directory the normalized logos are written to and read from.
+    #[serde(default = "images")]
+    pub images: String,
+    /// fastfetch logo type, e.g. `kitty-direct`, `sixel` or `auto`.
+    #[serde(default = "logo_type", rename = "type")]
+    pub kind: String,
+    /// Logo size in terminal cells; with both set, images are cropped to
+    /// this aspect ratio and resized.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Text rendition written next to each logo, `ascii` or `ansi`; the
+    /// logo types `file` and `file-raw` show it instead of the image.
+    #[serde(default)]
+    pub art: Option<Art>,
+    #[serde(default)]
+    pub padding: Padding,
+    /// fastfetch modules, names or objects; the modules of an existing
+    /// configuration are kept when empty.
+    #[serde(default)]
+    pub modules: Vec<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Padding {
+    pub top: Option<u32>,
+    pub left: Option<u32>,
+    pub right: Option<u32>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            logo: random(),
+            images: images(),
+            kind: logo_type(),
+            width: None,
+            height: None,
+            art: None,
+            padding: Padding::default(),
+            modules: Vec::new(),
+        }
+    }
+}
+
+fn random() -> String {
+    RANDOM.to_string()
+}
+
+fn images() -> String {
+    "~/.config/fastfetch/logos".to_string()
+}
+
+fn logo_type() -> String {
+    "auto".to_string()
+}
+
+/// The logo chosen for the configuration.
+enum Logo {
+    /// A different installed image per shell start.
+    Random,
+    /// One shipped image.
+    Image(PathBuf),
+}
+
+/// Writes or updates the fastfetch configuration in the target path so that
+/// it shows one of the shipped images, or a random one, as its logo. In an
+/// existing configuration only the `logo` and `modules` keys are rewritten.
+pub(crate) fn install(
+    ctx: &Context,
+    choice: &FileEntry,
+    previous: Option<&Record>,
+) -> Result<Outcome, InstallError> {
+    ctx.log.action("Installing", "fastfetch configuration");
+    let source_path = choice
+        .source_path
+        .as_ref()
+        .ok_or_else(|| InstallError::MissingPath("Source path for images is missing.".to_string()))?;
+    let target_path = choice
+        .target_path
+        .as_ref()
+        .ok_or_else(|| InstallError::MissingPath("Target path for fastfetch config is missing.".to_string()))?;
+
+    let settings = load_settings(ctx)?;
+    let images_dir = PathBuf::from(expand_home(&settings.images));
+    let shipped = list_images(Path::new(source_path))?;
+    let logo = select_logo(ctx, &settings, &shipped)?;
+
+    // The logo is read from the images directory; normalize what it needs there.
+    let needed: Vec<&PathBuf> = match &logo {
+        Logo::Random => shipped.iter().collect(),
+        Logo::Image(image) => vec![image],
+    };
+    let size = settings.width.zip(settings.height).filter(|&(width, height)| width > 0 && height > 0);
+    let normalize = Normalize { size, art: settings.art };
+    let mut files = Vec::new();
+    let mut changed = false;
+    for image in needed {
+        let normalized = normalize.write(ctx, image, &images_dir)?;
+        changed |= normalized.changed;
+        files.extend(normalized.files.iter().map(|file| file.to_string_lossy().into_owned()));
+    }
+
+    // `file` and `file-raw` print text, the rendition when there is one.
+    let extension = match settings.art {
+        Some(_) if settings.kind.starts_with("file") => "txt",
+        _ => "png",
+    };
+    let source = match &logo {
+        Logo::Random => format!(
+            "$(find \"{}\" -maxdepth 1 -name \"*.{}\" | shuf -n 1)",
+            images_dir.display(),
+            extension
+        ),
+        Logo::Image(image) => {
+            images_dir.join(format!("{}.{}", image_name(image), extension)).to_string_lossy().into_owned()
+        }
+    };
+
+    let target = Path::new(target_path);
+    let (content, existing) = match fs::read_to_string(target) {
+        Ok(content) => match jsonc::parse(&content) {
+            Ok(value) if value.is_object() => (Some(content), Some(value)),
+            Ok(_) | Err(_) if ctx.force => {
+                let backup = format!("{}.bak", target_path);
+                fs::write(&backup, &content)
+                    .map_err(|e| InstallError::io(format!("Failed to back up {}", target_path), e))?;
+                ctx.log.warn(format!(
+                    "Replacing {}, it is not a valid configuration (--force); it was saved as {}.",
+                    target_path, backup
+                ));
+                (None, None)
+            }
+            Ok(_) => return Err(InstallError::Parse(format!("{} is not a JSON object.", target_path))),
+            Err(e) => {
+                return Err(InstallError::Parse(format!(
+                    "Invalid fastfetch configuration {}: {}. Use --force to replace it.",
+                    target_path, e
+                )))
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (None, None),
+        Err(e) => return Err(InstallError::io(format!("Failed to read {}", target_path), e)),
+    };
+
+    let config = update(existing.clone().unwrap_or_else(|| json!({ "$schema": SCHEMA })), &settings, &source);
+    if existing.as_ref() == Some(&config) && !changed {
+        return Ok(Outcome::Skipped(format!("{} already uses this logo.", target_path)));
+    }
+
+    // Only a configuration hyde-ext created is removed again on uninstall;
+    // in any other, the keys it replaces are restored.
+    let created = existing.is_none() || previous.is_some_and(|record| record.files.contains(target_path));
+    let mut replaced = previous.map(|record| record.replaced.clone()).unwrap_or_default();
+    let content = match (content, &existing) {
+        (Some(mut content), Some(existing)) => {
+            for key in ["logo", "modules"] {
+                if config.get(key) == existing.get(key) {
+                    continue;
+                }
+                if !created {
+                    let before = existing.get(key).unwrap_or(&Value::Null);
+                    replaced.entry(key.to_string()).or_insert_with(|| before.to_string());
+                }
+                content = jsonc::set(&content, key, config.get(key))
+                    .ok_or_else(|| InstallError::Parse(format!("Failed to edit {}.", target_path)))?;
+            }
+            content
+        }
+        _ => format!("{}\n", serde_json::to_string_pretty(&config).unwrap_or_default()),
+    };
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| InstallError::io(format!("Failed to create directory {}", parent.display()), e))?;
+    }
+    fs::write(target, content).map_err(|e| InstallError::io(format!("Failed to write {}", target_path), e))?;
+    ctx.log.detail(format!("logo: {}", source));
+
+    // Logos written by an earlier install stay recorded until uninstall.
+    for file in previous.map_or(&[][..], |record| &record.files) {
+        if file != target_path && !files.contains(file) {
+            files.push(file.clone());
+        }
+    }
+    if created {
+        files.push(target_path.clone());
+        replaced.clear();
+    }
+    ctx.log.success(format!("wrote {}", target_path));
+    Ok(Outcome::Applied(Box::new(Record {
+        target_path: Some(target_path.clone()),
+        files,
+        replaced,
+        ..Record::default()
+    })))
+}
+
+/// Restores the keys of the configuration replaced by `install`, then
+/// removes the logos and a configuration hyde-ext created.
+pub(crate) fn uninstall(ctx: &Context, record: &Record) -> Result<Outcome, InstallError> {
+    if let (Some(target_path), false) = (&record.target_path, record.replaced.is_empty()) {
+        restore(ctx, target_path, &record.replaced)?;
+    }
+    remove_files(ctx, record)
+}
+
+/// Sets each key in `replaced` back to the JSON it had before, removing the
+/// keys that were absent.
+fn restore(ctx: &Context, target_path: &str, replaced: &BTreeMap<String, String>) -> Result<(), InstallError> {
+    let mut content = match fs::read_to_string(target_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            ctx.log.debug(format!("Already removed: {}", target_path));
+            return Ok(());
+        }
+        Err(e) => return Err(InstallError::io(format!("Failed to read {}", target_path), e)),
+    };
+    for (key, before) in replaced {
+        let before: Value = serde_json::from_str(before)
+            .map_err(|e| InstallError::Parse(format!("Invalid recorded value of {}: {}", key, e)))?;
+        content = jsonc::set(&content, key, Some(&before).filter(|value| !value.is_null()))
+            .ok_or_else(|| InstallError::Conflict(format!("{} is no longer a JSON object.", target_path)))?;
+    }
+    fs::write(target_path, content).map_err(|e| InstallError::io(format!("Failed to write {}", target_path), e))?;
+    let keys: Vec<&str> = replaced.keys().map(String::as_str).collect();
+    ctx.log.detail(format!("restored {} in {}", keys.join(", "), target_path));
+    Ok(())
+}
+
+/// Sets the logo of `config` and, when the catalog declares them, its
+/// modules. Other logo options and top-level keys are kept.
+fn update(mut config: Value, settings: &Settings, source: &str) -> Value {
+    let object = config.as_object_mut().expect("configuration is an object");
+    let logo = object.entry("logo").or_insert_with(|| Value::Object(Map::new()));
+    if !logo.is_object() {
+        *logo = Value::Object(Map::new());
+    }
+    let logo = logo.as_object_mut().expect("logo is an object");
+    logo.insert("type".to_string(), json!(settings.kind));
+    logo.insert("source".to_string(), json!(source));
+    for (key, value) in [("width", settings.width), ("height", settings.height)] {
+        if let Some(value) = value {
+            logo.insert(key.to_string(), json!(value));
+        }
+    }
+    let padding = [("top", settings.padding.top), ("left", settings.padding.left), ("right", settings.padding.right)];
+    if padding.iter().any(|(_, value)| value.is_some()) {
+        let padding: Map<String, Value> =
+            padding.iter().filter_map(|(key, value)| value.map(|value| (key.to_string(), json!(value)))).collect();
+        logo.insert("padding".to_string(), Value::Object(padding));
+    }
+    if !settings.modules.is_empty() {
+        object.insert("modules".to_string(), Value::Array(settings.modules.clone()));
+    }
+    config
+}
+
+/// Picks the logo named with `--fastfetch-logo`, interactively, or the
+/// `logo` of the catalog when stdin is not a terminal.
+fn select_logo(ctx: &Context, settings: &Settings, images: &[PathBuf]) -> Result<Logo, InstallError> {
+    let names: Vec<String> = images.iter().map(|image| image_name(image)).collect();
+    let find = |name: &str| -> Result<Logo, InstallError> {
+        if name.eq_ignore_ascii_case(RANDOM) {
+            return Ok(Logo::Random);
+        }
+        match names.iter().position(|candidate| candidate.eq_ignore_ascii_case(name)) {
+            Some(index) => Ok(Logo::Image(images[index].clone())),
+            None => Err(InstallError::Selection(format!(
+                "No fastfetch image matches '{}'. Available images: {}, {}",
+                name,
+                RANDOM,
+                names.join(", ")
+            ))),
+        }
+    };
+
+    if let Some(name) = &ctx.fastfetch.logo {
+        return find(name);
+    }
+    if !io::stdin().is_terminal() || ctx.assume_yes {
+        return find(&settings.logo);
+    }
+
+    let preview = ctx.fastfetch.preview.unwrap_or_else(Preview::detect);
+    eprint!("{}", preview::gallery(images, preview, 1));
+
+    let mut items = vec![format!("{} (a different image per shell start)", RANDOM)];
+    items.extend(names.iter().enumerate().map(|(index, name)| format!("{}. {}", index + 1, name)));
+    let default = names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(&settings.logo))
+        .map_or(0, |index| index + 1);
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{}\n", "Select the fastfetch logo:".yellow()))
+        .items(&items)
+        .default(default)
+        .interact_opt()
+        .map_err(|e| InstallError::Selection(format!("Failed to select the logo: {}", e)))?
+        .ok_or_else(|| InstallError::Selection("No fastfetch logo was selected.".to_string()))?;
+    Ok(match index {
+        0 => Logo::Random,
+        index => Logo::Image(images[index - 1].clone()),
+    })
+}
+
+/// `hyde-ext fastfetch preview [IMAGE]...`: prints the images of the
+/// fastfetch entry, or only the named ones, as a gallery on stdout.
+pub fn preview_images(ctx: &Context, names: &[String]) {
+    let result = manager::catalog(ctx)
+        .into_iter()
+        .find(|entry| entry.handler == "fastfetch")
+        .and_then(|entry| entry.source_path)
+        .ok_or_else(|| InstallError::MissingPath("No fastfetch entry found in the catalog.".to_string()))
+        .and_then(|source| list_images(Path::new(&source)))
+        .and_then(|images| {
+            let mut selected = Vec::new();
+            for name in names {
+                match images.iter().find(|image| image_name(image).eq_ignore_ascii_case(name)) {
+                    Some(image) => selected.push(image.clone()),
+                    None => {
+                        let available: Vec<String> = images.iter().map(|image| image_name(image)).collect();
+                        return Err(InstallError::Selection(format!(
+                            "No fastfetch image matches '{}'. Available images: {}",
+                            name,
+                            available.join(", ")
+                        )));
+                    }
+                }
+            }
+            Ok(if names.is_empty() { images } else { selected })
+        });
+
+    match result {
+        Ok(images) => {
+            let preview = ctx.fastfetch.preview.unwrap_or_else(Preview::detect);
+            print!("{}", preview::gallery(&images, preview, 1));
+        }
+        Err(e) => {
+            ctx.log.error(&e);
+            ctx.report.failed("fastfetch", Some("fastfetch"), e.kind(), e.to_string());
+        }
+    }
+}
+
+/// Images in `dir`, sorted by name.
+fn list_images(dir: &Path) -> Result<Vec<PathBuf>, InstallError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| InstallError::io(format!("Error reading source directory {}", dir.display()), e))?;
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+/// File name without extension, as shown in the picker and used by `--fastfetch-logo`.
+fn image_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn load_settings(ctx: &Context) -> Result<Settings, InstallError> {
+    let path = ctx.assets_dir.join("FastFetchAssets").join("FastFetchAssets.toml");
+    let content = fs::read_to_string(&path)
+        .map_err(|e| InstallError::io(format!("Failed to read fastfetch settings {}", path.display()), e))?;
+    let catalog: Catalog = toml::from_str(&content)
+        .map_err(|e| InstallError::Parse(format!("Invalid fastfetch settings {}: {}", path.display(), e.message())))?;
+    Ok(catalog.fastfetch)
+}
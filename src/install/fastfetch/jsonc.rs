@@ -0,0 +1,243 @@
+use std::ops::Range;
+
+use serde_json::Value;
+
+/// A member of the top-level object of a JSONC document: the byte offset of
+/// its key and the byte range of its value.
+struct Member {
+    key: String,
+    start: usize,
+    value: Range<usize>,
+}
+
+/// Parses JSONC (JSON with comments and trailing commas) into a value.
+pub(super) fn parse(content: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(&strip(content))
+}
+
+/// Sets the top-level `key` of a JSONC object to `value`, or removes it when
+/// `value` is `None`. Only the text of that member changes, so comments and
+/// formatting elsewhere are kept. `None` when `content` is not an object.
+pub(super) fn set(content: &str, key: &str, value: Option<&Value>) -> Option<String> {
+    let (members, close) = members(content)?;
+    let index = members.iter().position(|member| member.key == key);
+    let mut edited = content.to_string();
+    match (index, value) {
+        (Some(index), Some(value)) => {
+            let member = &members[index];
+            edited.replace_range(member.value.clone(), &render(value, &indent_of(content, member.start)));
+        }
+        (Some(index), None) => {
+            let member = &members[index];
+            let range = match (members.get(index + 1), index.checked_sub(1).map(|previous| &members[previous])) {
+                // Up to the next key, so that its indentation stays in place.
+                (Some(next), _) => member.start..next.start,
+                // The last member goes with the comma after the previous value.
+                (None, Some(previous)) => previous.value.end..member.value.end,
+                (None, None) => member.start..skip_comma(content, member.value.end),
+            };
+            edited.replace_range(range, "");
+        }
+        (None, Some(value)) => match members.last() {
+            Some(last) => {
+                let indent = indent_of(content, last.start);
+                let member = format!(",\n{}{}: {}", indent, Value::from(key), render(value, &indent));
+                edited.insert_str(last.value.end, &member);
+            }
+            None => {
+                let member = format!("\n  {}: {}\n", Value::from(key), render(value, "  "));
+                edited.replace_range(close..close, &member);
+            }
+        },
+        (None, None) => {}
+    }
+    Some(edited)
+}
+
+/// `value` as pretty JSON whose following lines are indented like the member.
+fn render(value: &Value, indent: &str) -> String {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_default();
+    pretty.replace('\n', &format!("\n{}", indent))
+}
+
+/// The whitespace before the key at `start` on its line.
+fn indent_of(content: &str, start: usize) -> String {
+    let line_start = content[..start].rfind('\n').map_or(0, |index| index + 1);
+    content[line_start..start].chars().take_while(|c| c.is_whitespace()).collect()
+}
+
+/// The members of the top-level object and the offset of its closing brace.
+fn members(content: &str) -> Option<(Vec<Member>, usize)> {
+    let bytes = content.as_bytes();
+    let mut index = skip_blank(bytes, 0);
+    if bytes.get(index) != Some(&b'{') {
+        return None;
+    }
+    index += 1;
+    let mut members = Vec::new();
+    loop {
+        index = skip_blank(bytes, index);
+        match bytes.get(index)? {
+            b'}' => return Some((members, index)),
+            b',' => index += 1,
+            b'"' => {
+                let start = index;
+                let end = skip_string(bytes, start);
+                let key = serde_json::from_str(&content[start..end]).ok()?;
+                index = skip_blank(bytes, end);
+                if bytes.get(index) != Some(&b':') {
+                    return None;
+                }
+                let value_start = skip_blank(bytes, index + 1);
+                let value_end = skip_value(bytes, value_start);
+                members.push(Member { key, start, value: value_start..value_end });
+                index = value_end;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Skips whitespace and comments from `index`.
+fn skip_blank(bytes: &[u8], mut index: usize) -> usize {
+    loop {
+        match (bytes.get(index), bytes.get(index + 1)) {
+            (Some(c), _) if c.is_ascii_whitespace() => index += 1,
+            (Some(b'/'), Some(b'/')) => {
+                while bytes.get(index).is_some_and(|&c| c != b'\n') {
+                    index += 1;
+                }
+            }
+            (Some(b'/'), Some(b'*')) => {
+                index += 2;
+                while index < bytes.len() && !(bytes[index] == b'*' && bytes.get(index + 1) == Some(&b'/')) {
+                    index += 1;
+                }
+                index = (index + 2).min(bytes.len());
+            }
+            _ => return index,
+        }
+    }
+}
+
+/// The offset after the string starting at `index`.
+fn skip_string(bytes: &[u8], mut index: usize) -> usize {
+    index += 1;
+    while let Some(&c) = bytes.get(index) {
+        index += 1;
+        match c {
+            b'\\' => index += 1,
+            b'"' => return index,
+            _ => {}
+        }
+    }
+    bytes.len()
+}
+
+/// The offset after the value starting at `index`.
+fn skip_value(bytes: &[u8], mut index: usize) -> usize {
+    match bytes.get(index) {
+        Some(b'"') => skip_string(bytes, index),
+        Some(b'{' | b'[') => {
+            let mut depth = 0;
+            while let Some(&c) = bytes.get(index) {
+                match c {
+                    b'"' => {
+                        index = skip_string(bytes, index);
+                        continue;
+                    }
+                    b'/' if matches!(bytes.get(index + 1), Some(b'/' | b'*')) => {
+                        index = skip_blank(bytes, index);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return index + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                index += 1;
+            }
+            bytes.len()
+        }
+        _ => {
+            while bytes.get(index).is_some_and(|&c| !b",}] \t\r\n/".contains(&c)) {
+                index += 1;
+            }
+            index
+        }
+    }
+}
+
+/// The offset after a comma following `index`, or `index` when there is none.
+fn skip_comma(content: &str, index: usize) -> usize {
+    let next = skip_blank(content.as_bytes(), index);
+    if content.as_bytes().get(next) == Some(&b',') {
+        next + 1
+    } else {
+        index
+    }
+}
+
+/// Removes `//` and `/* */` comments and trailing commas from JSONC so that
+/// it parses as JSON.
+fn strip(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    // Trailing commas: drop a comma when only whitespace precedes `}` or `]`.
+    let mut result = String::with_capacity(stripped.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in stripped.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && stripped[index + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::Deserialize;
+
+use crate::context::Context;
+use crate::install::sync::Fnv1a;
+use crate::install::InstallError;
+
+use super::preview::{self, OPAQUE};
+
+/// Pixels of one terminal cell, about the cell size of common fonts. Logos
+/// are resized to their size in cells times this.
+const CELL: (u32, u32) = (10, 20);
+/// Size in cells of text renditions when the catalog sets no logo size.
+const ART_SIZE: (u32, u32) = (32, 16);
+/// Characters of ASCII renditions, from dark to bright.
+const RAMP: &[u8] = b" .:-=+*#%@";
+/// Changes whenever the output for the same image and settings changes, so
+/// that older cache entries are not used.
+const CACHE_VERSION: u32 = 1;
+
+/// Text rendition written next to each logo for terminals without image
+/// support; fastfetch shows it with the logo type `file` or `file-raw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Art {
+    /// Characters by brightness, readable everywhere.
+    Ascii,
+    /// Half blocks with 24-bit colors; needs `file-raw` to keep the escapes.
+    Ansi,
+}
+
+impl Art {
+    fn render(self, image: &DynamicImage, (columns, rows): (u32, u32)) -> String {
+        // Both renditions show two pixel rows per cell.
+        let fitted = image.resize(columns, rows * 2, FilterType::Lanczos3).to_rgba8();
+        let mut lines = match self {
+            Art::Ansi => preview::half_blocks(&fitted, columns, rows),
+            Art::Ascii => ascii(&fitted),
+        };
+        for line in &mut lines {
+            line.truncate(line.trim_end().len());
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// How logos are normalized, taken from the `[fastfetch]` table.
+pub(crate) struct Normalize {
+    /// Logo size in cells; the image is cropped to its aspect ratio and
+    /// resized when both are set, and only converted otherwise.
+    pub size: Option<(u32, u32)>,
+    pub art: Option<Art>,
+}
+
+/// Files written for one image.
+pub(crate) struct Normalized {
+    /// The PNG, and the text rendition when one is configured.
+    pub files: Vec<PathBuf>,
+    /// Whether any of them was created or changed.
+    pub changed: bool,
+}
+
+impl Normalize {
+    /// Writes `image` to `dir` as `<name>.png`, cropped and resized, and its
+    /// text rendition as `<name>.txt`. Results are cached by the content of
+    /// the image and the settings, so installing again does not decode it.
+    pub fn write(&self, ctx: &Context, image: &Path, dir: &Path) -> Result<Normalized, InstallError> {
+        let bytes = fs::read(image).map_err(|e| InstallError::io(format!("Failed to read {}", image.display()), e))?;
+        let key = format!("{:016x}", self.fingerprint(&bytes));
+        let outputs = match self.cached(&key) {
+            Some(outputs) => {
+                ctx.log.debug(format!("Using the cached logo of {}", image.display()));
+                outputs
+            }
+            None => {
+                let outputs = self.render(image, &bytes)?;
+                self.store(ctx, &key, &outputs);
+                outputs
+            }
+        };
+
+        fs::create_dir_all(dir)
+            .map_err(|e| InstallError::io(format!("Failed to create directory {}", dir.display()), e))?;
+        let name = image.file_stem().unwrap_or_default().to_string_lossy();
+        let mut normalized = Normalized { files: Vec::new(), changed: false };
+        for (extension, data) in outputs {
+            let target = dir.join(format!("{}.{}", name, extension));
+            if fs::read(&target).ok().as_ref() != Some(&data) {
+                fs::write(&target, &data)
+                    .map_err(|e| InstallError::io(format!("Failed to write {}", target.display()), e))?;
+                ctx.log.debug(format!("Wrote {} from {}", target.display(), image.display()));
+                normalized.changed = true;
+            }
+            normalized.files.push(target);
+        }
+        Ok(normalized)
+    }
+
+    /// The PNG and, when configured, the text rendition of `bytes`.
+    fn render(&self, image: &Path, bytes: &[u8]) -> Result<Vec<(&'static str, Vec<u8>)>, InstallError> {
+        let decoded = image::load_from_memory(bytes)
+            .map_err(|e| InstallError::Parse(format!("{} is not a supported image: {}", image.display(), e)))?;
+        let decoded = match self.size {
+            Some((columns, rows)) => {
+                let (width, height) = (columns * CELL.0, rows * CELL.1);
+                crop(decoded, width, height).resize_exact(width, height, FilterType::Lanczos3)
+            }
+            None => decoded,
+        };
+
+        let mut png = Vec::new();
+        decoded
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| InstallError::io(format!("Failed to encode {}", image.display()), io::Error::other(e)))?;
+        let mut outputs = vec![("png", png)];
+        if let Some(art) = self.art {
+            outputs.push(("txt", art.render(&decoded, self.size.unwrap_or(ART_SIZE)).into_bytes()));
+        }
+        Ok(outputs)
+    }
+
+    /// FNV-1a of the image and everything that changes the output.
+    fn fingerprint(&self, bytes: &[u8]) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write(bytes);
+        hasher.write(format!("{}:{:?}:{:?}:{:?}", CACHE_VERSION, CELL, self.size, self.art).as_bytes());
+        hasher.finish()
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        if self.art.is_some() {
+            &["png", "txt"]
+        } else {
+            &["png"]
+        }
+    }
+
+    fn cached(&self, key: &str) -> Option<Vec<(&'static str, Vec<u8>)>> {
+        let dir = cache_dir()?;
+        self.extensions()
+            .iter()
+            .map(|extension| Some((*extension, fs::read(dir.join(format!("{}.{}", key, extension))).ok()?)))
+            .collect()
+    }
+
+    /// Saves `outputs` to the cache; a cache that cannot be written only
+    /// makes the next install slower.
+    fn store(&self, ctx: &Context, key: &str, outputs: &[(&'static str, Vec<u8>)]) {
+        let Some(dir) = cache_dir() else {
+            return;
+        };
+        let result = fs::create_dir_all(&dir).and_then(|_| {
+            outputs
+                .iter()
+                .try_for_each(|(extension, data)| fs::write(dir.join(format!("{}.{}", key, extension)), data))
+        });
+        if let Err(e) = result {
+            ctx.log.debug(format!("Failed to cache the logo in {}: {}", dir.display(), e));
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/hyde-ext/fastfetch`, falling back to `~/.cache/hyde-ext/fastfetch`.
+fn cache_dir() -> Option<PathBuf> {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache_home.join("hyde-ext").join("fastfetch"))
+}
+
+/// The centered part of `image` with the aspect ratio of `width` x `height`.
+fn crop(image: DynamicImage, width: u32, height: u32) -> DynamicImage {
+    let (image_width, image_height) = (u64::from(image.width()), u64::from(image.height()));
+    let (width, height) = (u64::from(width), u64::from(height));
+    if image_width * height > width * image_height {
+        let cropped = (image_height * width / height).max(1);
+        image.crop_imm(((image_width - cropped) / 2) as u32, 0, cropped as u32, image_height as u32)
+    } else {
+        let cropped = (image_width * height / width).max(1);
+        image.crop_imm(0, ((image_height - cropped) / 2) as u32, image_width as u32, cropped as u32)
+    }
+}
+
+/// One character per two pixel rows, by brightness; transparent pixels stay blank.
+fn ascii(image: &RgbaImage) -> Vec<String> {
+    let mut lines = Vec::new();
+    for row in (0..image.height()).step_by(2) {
+        let mut line = String::new();
+        for column in 0..image.width() {
+            let pixels: Vec<_> = (row..(row + 2).min(image.height()))
+                .map(|y| image.get_pixel(column, y))
+                .filter(|pixel| pixel[3] >= OPAQUE)
+                .collect();
+            if pixels.is_empty() {
+                line.push(' ');
+                continue;
+            }
+            let luma = pixels
+                .iter()
+                .map(|pixel| 299 * u32::from(pixel[0]) + 587 * u32::from(pixel[1]) + 114 * u32::from(pixel[2]))
+                .sum::<u32>()
+                / (1000 * pixels.len() as u32);
+            let index = (luma as usize * (RAMP.len() - 1) + 127) / 255;
+            line.push(char::from(RAMP[index]));
+        }
+        lines.push(line);
+    }
+    lines
+}
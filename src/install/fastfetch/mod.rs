@@ -0,0 +1,45 @@
+mod config;
+mod jsonc;
+mod logo;
+mod preview;
+
+use std::path::Path;
+
+use crate::context::Context;
+use crate::install::sync::sync_dir;
+use crate::install::{FileEntry, InstallError, Outcome};
+use crate::state::Record;
+
+pub(crate) use config::{install as configure, uninstall as unconfigure};
+pub use config::preview_images;
+pub use preview::Preview;
+
+/// Syncs the image directory of the entry into the target directory.
+pub(crate) fn install(ctx: &Context, choice: &FileEntry, previous: Option<&Record>) -> Result<Outcome, InstallError> {
+    ctx.log.action("Installing", "FastFetch (alter neofetch) terminal images");
+    let source_path = choice
+        .source_path
+        .as_ref()
+        .ok_or_else(|| InstallError::MissingPath("Source path for images is missing.".to_string()))?;
+    let target_path = choice
+        .target_path
+        .as_ref()
+        .ok_or_else(|| InstallError::MissingPath("Target path for images is missing.".to_string()))?;
+
+    let synced = sync_dir(ctx, Path::new(source_path), Path::new(target_path), previous)?;
+    let record = Box::new(Record {
+        files: synced.files,
+        checksums: synced.checksums,
+        ..Record::default()
+    });
+    if !synced.failed.is_empty() {
+        return Err(InstallError::Partial { failed: synced.failed, record: Some(record) });
+    }
+    // Kept files drop out of the record, so uninstall leaves them alone.
+    if synced.copied == 0 && synced.kept.is_empty() {
+        return Ok(Outcome::Skipped(format!("{} is up to date.", target_path)));
+    }
+
+    ctx.log.success(format!("copied {} image(s) to {}", synced.copied, target_path));
+    Ok(Outcome::Applied(record))
+}
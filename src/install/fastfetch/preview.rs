@@ -0,0 +1,266 @@
+use std::env;
+use std::fmt::Write;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::{ImageFormat, RgbaImage};
+
+/// Size of one thumbnail in terminal cells.
+const COLUMNS: u32 = 16;
+const ROWS: u32 = 8;
+/// Pixel size of kitty and sixel thumbnails, about the cell size of common fonts.
+const PIXELS: u32 = 128;
+/// Columns between two thumbnails.
+const GAP: usize = 2;
+/// Alpha below which a pixel is left transparent.
+pub(super) const OPAQUE: u8 = 128;
+
+/// How image previews are drawn in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preview {
+    /// kitty graphics protocol (kitty, WezTerm, Ghostty).
+    Kitty,
+    /// DEC sixel graphics (foot, mlterm, contour).
+    Sixel,
+    /// Half-block characters with 24-bit colors, for every other terminal.
+    Blocks,
+    /// Names only.
+    None,
+}
+
+impl Preview {
+    /// Accepted values of `--fastfetch-preview` and `--protocol`.
+    pub const NAMES: [&'static str; 4] = ["kitty", "sixel", "blocks", "none"];
+
+    pub fn from_name(name: &str) -> Option<Preview> {
+        match name {
+            "kitty" => Some(Preview::Kitty),
+            "sixel" => Some(Preview::Sixel),
+            "blocks" => Some(Preview::Blocks),
+            "none" => Some(Preview::None),
+            _ => None,
+        }
+    }
+
+    /// Guesses the protocol from the environment the terminal sets; asking
+    /// the terminal itself would need raw mode on stdin.
+    pub fn detect() -> Preview {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || ["WezTerm", "ghostty"].contains(&program.as_str())
+        {
+            Preview::Kitty
+        } else if ["foot", "mlterm", "contour", "yaft"].iter().any(|name| term.starts_with(name)) {
+            Preview::Sixel
+        } else {
+            Preview::Blocks
+        }
+    }
+}
+
+/// Thumbnails of `images` in rows as wide as the terminal, each with its
+/// index (starting at `first`) and name below it.
+pub(crate) fn gallery(images: &[PathBuf], preview: Preview, first: usize) -> String {
+    let width = term_size::dimensions().map_or(80, |(width, _)| width);
+    let per_row = (width / (COLUMNS as usize + GAP)).max(1);
+    let mut output = String::new();
+    for (row, chunk) in images.chunks(per_row).enumerate() {
+        let labels: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                let name = image.file_stem().unwrap_or_default().to_string_lossy();
+                let label = format!("{}. {}", first + row * per_row + index, name);
+                label.chars().take(COLUMNS as usize).collect()
+            })
+            .collect();
+        match preview {
+            Preview::None => {}
+            Preview::Blocks => {
+                let blocks: Vec<Vec<String>> = chunk.iter().map(|image| blocks(image)).collect();
+                for line in 0..ROWS as usize {
+                    for cell in &blocks {
+                        output.push_str(&cell[line]);
+                        output.push_str(&" ".repeat(GAP));
+                    }
+                    output.push('\n');
+                }
+            }
+            Preview::Kitty | Preview::Sixel => {
+                // Reserve the rows, then draw every image from the saved top
+                // left corner so the cursor does not depend on the terminal.
+                output.push_str(&"\n".repeat(ROWS as usize));
+                let _ = write!(output, "\x1b[{}A", ROWS);
+                for image in chunk {
+                    output.push_str("\x1b7");
+                    match preview {
+                        Preview::Kitty => output.push_str(&kitty(image).unwrap_or_default()),
+                        _ => output.push_str(&sixel(image).unwrap_or_default()),
+                    }
+                    output.push_str("\x1b8");
+                    let _ = write!(output, "\x1b[{}C", COLUMNS as usize + GAP);
+                }
+                let _ = write!(output, "\r\x1b[{}B", ROWS);
+            }
+        }
+        let labels: Vec<String> =
+            labels.iter().map(|label| format!("{:width$}", label, width = COLUMNS as usize)).collect();
+        output.push_str(labels.join(&" ".repeat(GAP)).trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// The image fitted into `width` x `height` pixels, `None` when it cannot be decoded.
+fn load(path: &Path, width: u32, height: u32) -> Option<RgbaImage> {
+    Some(image::open(path).ok()?.resize(width, height, FilterType::Triangle).to_rgba8())
+}
+
+/// `ROWS` lines of `COLUMNS` cells showing the image, blank when it cannot be decoded.
+fn blocks(path: &Path) -> Vec<String> {
+    match load(path, COLUMNS, ROWS * 2) {
+        Some(image) => half_blocks(&image, COLUMNS, ROWS),
+        None => vec![" ".repeat(COLUMNS as usize); ROWS as usize],
+    }
+}
+
+/// `rows` lines of `columns` cells, each showing two pixels of `image` with
+/// `▀`: the upper one as foreground, the lower one as background color. An
+/// image smaller than the cells is centered.
+pub(super) fn half_blocks(image: &RgbaImage, columns: u32, rows: u32) -> Vec<String> {
+    let left = columns.saturating_sub(image.width()) / 2;
+    let top = (rows * 2).saturating_sub(image.height()) / 2;
+    let pixel = |x: u32, y: u32| -> Option<[u8; 3]> {
+        let (x, y) = (x.checked_sub(left)?, y.checked_sub(top)?);
+        let pixel = image.get_pixel_checked(x, y)?;
+        (pixel[3] >= OPAQUE).then_some([pixel[0], pixel[1], pixel[2]])
+    };
+
+    let mut lines = Vec::new();
+    for row in 0..rows {
+        let mut line = String::new();
+        for column in 0..columns {
+            let (upper, lower) = (pixel(column, row * 2), pixel(column, row * 2 + 1));
+            match (upper, lower) {
+                (None, None) => line.push(' '),
+                (Some([r, g, b]), None) => {
+                    let _ = write!(line, "\x1b[38;2;{};{};{}m▀\x1b[0m", r, g, b);
+                }
+                (None, Some([r, g, b])) => {
+                    let _ = write!(line, "\x1b[38;2;{};{};{}m▄\x1b[0m", r, g, b);
+                }
+                (Some([r, g, b]), Some([r2, g2, b2])) => {
+                    let _ = write!(line, "\x1b[38;2;{};{};{};48;2;{};{};{}m▀\x1b[0m", r, g, b, r2, g2, b2);
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The image as PNG in kitty graphics escapes, scaled by the terminal to
+/// `COLUMNS` x `ROWS` cells without moving the cursor.
+fn kitty(path: &Path) -> Option<String> {
+    let image = load(path, PIXELS, PIXELS)?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).ok()?;
+    let data = base64(&png);
+
+    // The payload is sent in chunks of at most 4096 bytes.
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut output = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).ok()?;
+        if index == 0 {
+            let _ = write!(output, "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\", COLUMNS, ROWS, more, chunk);
+        } else {
+            let _ = write!(output, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    Some(output)
+}
+
+/// The image as sixel graphics with a 6x6x6 color cube palette.
+fn sixel(path: &Path) -> Option<String> {
+    let image = load(path, PIXELS, PIXELS)?;
+    let (width, height) = image.dimensions();
+    let level = |value: u8| (u32::from(value) * 5 + 127) / 255;
+    let color = |x: u32, y: u32| -> Option<u32> {
+        let pixel = image.get_pixel(x, y);
+        (pixel[3] >= OPAQUE).then(|| level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]))
+    };
+
+    // `P2=1`: transparent pixels keep the background.
+    let mut output = format!("\x1bP0;1q\"1;1;{};{}", width, height);
+    for index in 0..216 {
+        let (r, g, b) = (index / 36, index / 6 % 6, index % 6);
+        let _ = write!(output, "#{};2;{};{};{}", index, r * 20, g * 20, b * 20);
+    }
+    for band in (0..height).step_by(6) {
+        let mut colors: Vec<u32> =
+            (0..width).flat_map(|x| (band..(band + 6).min(height)).filter_map(move |y| color(x, y))).collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for (position, &index) in colors.iter().enumerate() {
+            let _ = write!(output, "#{}", index);
+            // One character per column: bit n is set when pixel n of the band has this color.
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = (0..6u32)
+                    .filter(|&bit| band + bit < height && color(x, band + bit) == Some(index))
+                    .fold(0u8, |bits, bit| bits | (1 << bit));
+                let c = char::from(63 + bits);
+                run = match run {
+                    Some((previous, count)) if previous == c => Some((c, count + 1)),
+                    Some((previous, count)) => {
+                        push_run(&mut output, previous, count);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((c, count)) = run {
+                push_run(&mut output, c, count);
+            }
+            // `$` returns to the start of the band for the next color, `-` moves to the next band.
+            output.push(if position + 1 < colors.len() { '$' } else { '-' });
+        }
+        if colors.is_empty() {
+            output.push('-');
+        }
+    }
+    output.push_str("\x1b\\");
+    Some(output)
+}
+
+fn push_run(output: &mut String, c: char, count: usize) {
+    if count > 3 {
+        let _ = write!(output, "!{}{}", count, c);
+    } else {
+        output.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+/// Standard base64 with padding, as kitty expects it.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(value >> (18 - 6 * index) & 63) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -1,8 +1,11 @@
 use colored::*;
 use dialoguer::{theme::ColorfulTheme, MultiSelect};
+use prettytable::{row, Table};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{fs, path::Path};
 use regex::Regex;
 
+use crate::context::Context;
 use crate::install::configs;
 use crate::install::fastfetch;
 use crate::install::packages;
@@ -10,73 +13,339 @@ use crate::install::scripts;
 use crate::install::ufw;
 use crate::install::FileConfig;
 use crate::install::FileEntry;
+use crate::install::{files_present, remove_files, InstallError, Outcome};
+use crate::privilege::Keepalive;
+use crate::report::OutputFormat;
+use crate::state::{Record, State};
 
-pub fn install_resources() {
-    let asset_folders = vec!["Configs", "FastFetchAssets", "Packages", "Scripts", "UFW"];
-    let asset_choices = gather_asset_choices(&asset_folders);
-
-    for selected_choice in make_choices(&asset_choices) {
-        if std::env::var("DEBUG").unwrap_or_default() == "true" {
-            println!(
-                "{} Selected choice details: Handler: {}, Title: {}, Source Path: {:?}, Target Path: {:?}",
-                ":: Debug:".blue(),
-                selected_choice.handler,
-                selected_choice.title.bright_yellow(),
-                selected_choice.source_path,
-                selected_choice.target_path
-            );
-        }
-        
-        if selected_choice.handler == "configs" {
-            configs::install(&selected_choice);
-            if let Some(path) = &selected_choice.target_path {
-                if path.contains("zshrc") {
-                    configs::modify_zshrc(&selected_choice);
-                }
+const ASSET_FOLDERS: [&str; 5] = ["Configs", "FastFetchAssets", "Packages", "Scripts", "UFW"];
+
+/// How catalog entries are chosen for `install` and `uninstall`.
+pub enum Selection {
+    /// Ask interactively.
+    Prompt,
+    /// Every entry (`--all`).
+    All,
+    /// Entries marked `default = true` (`--defaults`).
+    Defaults,
+    /// Entries matching the given titles (`--select`).
+    Titles(Vec<String>),
+}
+
+pub fn install_resources(ctx: &Context, selection: &Selection) {
+    let asset_choices = gather_asset_choices(ctx, &ASSET_FOLDERS);
+    match make_choices(ctx, &asset_choices, selection) {
+        Ok(selected_choices) => install_entries(ctx, &selected_choices),
+        Err(e) => report_failure(ctx, "selection", None, &e),
+    }
+}
+
+/// Runs the handler of every entry, records the applied ones in the install
+/// state and prints the summary.
+pub(super) fn install_entries(ctx: &Context, selected_choices: &[FileEntry]) {
+    let mut state = load_state(ctx);
+
+    let root: Vec<&str> =
+        selected_choices.iter().filter(|choice| choice.requires_root).map(|choice| choice.title.as_str()).collect();
+    let (_keepalive, denied) = match authenticate(ctx, &root) {
+        Ok(keepalive) => (keepalive, None),
+        Err(e) => (None, Some(e)),
+    };
+
+    for (index, selected_choice) in selected_choices.iter().enumerate() {
+        ctx.log.debug(format!(
+            "Selected choice details: Handler: {}, Title: {}, Source Path: {:?}, Target Path: {:?}",
+            selected_choice.handler,
+            selected_choice.title.bright_yellow(),
+            selected_choice.source_path,
+            selected_choice.target_path
+        ));
+
+        let title = &selected_choice.title;
+        let handler = Some(selected_choice.handler.as_str());
+        ctx.report.started(title, handler);
+
+        if let (Some(e), true) = (&denied, selected_choice.requires_root) {
+            report_failure(ctx, title, handler, e);
+            if ctx.fail_fast {
+                skip_remaining(ctx, selected_choices[index + 1..].iter().map(|c| (&c.title, &c.handler)));
+                break;
             }
+            continue;
+        }
+
+        let outcome = if selected_choice.handler == "configs" {
+            configs::install(ctx, selected_choice)
         }
         else if selected_choice.handler == "assets" {
-            fastfetch::install(&selected_choice);
+            fastfetch::install(ctx, selected_choice, state.get(title))
+        }
+        else if selected_choice.handler == "fastfetch" {
+            fastfetch::configure(ctx, selected_choice, state.get(title))
         }
         else if selected_choice.handler == "packages" {
-            packages::install(&selected_choice);
+            packages::install(ctx, selected_choice)
         }
         else if selected_choice.handler == "scripts" {
-            scripts::install(&selected_choice);
+            scripts::install(ctx, selected_choice)
         }
         else if selected_choice.handler == "ufw" {
-            ufw::install(&selected_choice);
+            ufw::install(ctx, selected_choice)
+        }
+        else {
+            Ok(Outcome::Skipped(format!("No handler available for '{}'.", title)))
+        };
+
+        match outcome {
+            Ok(Outcome::Applied(record)) => {
+                save_record(&mut state, selected_choice, *record);
+                ctx.report.applied(title, handler, "installed");
+            }
+            Ok(Outcome::Skipped(reason)) => ctx.report.skipped(title, handler, reason),
+            Err(mut e) => {
+                // What was applied before the failure is recorded, so that
+                // status and uninstall know about it.
+                if let InstallError::Partial { record: Some(record), .. } = &mut e {
+                    save_record(&mut state, selected_choice, *std::mem::take(record));
+                }
+                report_failure(ctx, title, handler, &e);
+                if ctx.fail_fast {
+                    skip_remaining(ctx, selected_choices[index + 1..].iter().map(|c| (&c.title, &c.handler)));
+                    break;
+                }
+            }
         }
     }
+
+    save_state(ctx, &state);
+    ctx.report.print_table();
 }
 
-fn gather_asset_choices(asset_folders: &[&str]) -> Vec<FileEntry> {
-    let mut asset_choices = Vec::new();
+/// Shows every catalog entry and whether hyde-ext installed it.
+pub fn status_resources(ctx: &Context) {
+    let state = load_state(ctx);
+    let mut table = Table::new();
+    table.set_titles(row!["Entry", "Handler", "Status"]);
 
-    for folder in asset_folders {
-        let conf_file_path = if cfg!(debug_assertions) {
-            format!("assets/{}/{}.toml", folder, folder)
-        } else {
-            format!("/usr/share/hyde-ext/assets/{}/{}.toml", folder, folder)
+    for choice in gather_asset_choices(ctx, &ASSET_FOLDERS) {
+        let (installed, status) = match state.get(&choice.title) {
+            Some(record) if record.handler == "packages" => (true, packages::status(record)),
+            Some(record) => match is_present(record) {
+                Some(true) => (true, "installed".to_string()),
+                Some(false) => (true, "installed, modified since".to_string()),
+                None => (true, "installed".to_string()),
+            },
+            None => (false, "not installed".to_string()),
         };
-        if std::env::var("DEBUG").unwrap_or_default() == "true" {
-            println!(
-                "{} Checking existence for: {}",
-                ":: Debug:".blue(),
-                conf_file_path.bright_yellow()
-            );
+
+        ctx.report.status(&choice.title, Some(&choice.handler), installed, status.clone());
+        let status = if installed { status.green() } else { status.normal() };
+        table.add_row(row![choice.title, choice.handler, status]);
+    }
+
+    if ctx.report.format() == OutputFormat::Text {
+        table.printstd();
+    }
+}
+
+/// Reverts the changes of entries previously installed by hyde-ext.
+pub fn uninstall_resources(ctx: &Context, selection: &Selection) {
+    let mut state = load_state(ctx);
+    if state.entries.is_empty() {
+        ctx.log.info("Nothing was installed by hyde-ext.");
+        return;
+    }
+
+    let titles: Vec<&str> = state.entries.iter().map(|record| record.title.as_str()).collect();
+    let selections = match select_titles(&titles, &vec![true; titles.len()], selection) {
+        Ok(Some(indices)) => Some(indices),
+        Err(e) => {
+            report_failure(ctx, "selection", None, &e);
+            return;
         }
-        if Path::new(&conf_file_path).exists() {
-            if std::env::var("DEBUG").unwrap_or_default() == "true" {
-                println!(
-                    "{} Found configuration for: {}",
-                    "  -> Debug:".blue(),
-                    conf_file_path.bright_yellow()
-                );
+        Ok(None) => MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "{}\n",
+                "Select what to uninstall:\n [a] All   [space] Toggle   [enter] Confirm".yellow()
+            ))
+            .items(&titles)
+            .interact_opt()
+            .unwrap_or_else(|e| {
+                ctx.log.error(format!("Failed to select options: {}", e));
+                None
+            }),
+    };
+
+    let indices = match selections {
+        Some(indices) if !indices.is_empty() => indices,
+        _ => {
+            ctx.log.warn("Nothing was selected. Aborting uninstallation.");
+            return;
+        }
+    };
+
+    let records: Vec<Record> = indices.iter().map(|&i| state.entries[i].clone()).collect();
+    let catalog = gather_asset_choices(ctx, &ASSET_FOLDERS);
+    let requires_root = |record: &Record| catalog.iter().any(|entry| entry.title == record.title && entry.requires_root);
+    let root: Vec<&str> =
+        records.iter().filter(|record| requires_root(record)).map(|record| record.title.as_str()).collect();
+    let (_keepalive, denied) = match authenticate(ctx, &root) {
+        Ok(keepalive) => (keepalive, None),
+        Err(e) => (None, Some(e)),
+    };
+
+    for (index, record) in records.iter().enumerate() {
+        let handler = Some(record.handler.as_str());
+        ctx.report.started(&record.title, handler);
+
+        if let (Some(e), true) = (&denied, requires_root(record)) {
+            report_failure(ctx, &record.title, handler, e);
+            if ctx.fail_fast {
+                skip_remaining(ctx, records[index + 1..].iter().map(|r| (&r.title, &r.handler)));
+                break;
             }
+            continue;
+        }
+        ctx.log.action("Uninstalling", &record.title);
+
+        let outcome = match record.handler.as_str() {
+            "configs" => configs::uninstall(ctx, record),
+            "assets" | "scripts" => remove_files(ctx, record),
+            "fastfetch" => fastfetch::unconfigure(ctx, record),
+            "packages" => packages::uninstall(ctx, record),
+            "ufw" => ufw::uninstall(ctx, record),
+            other => Ok(Outcome::Skipped(format!("Uninstall is not supported by the {} handler.", other))),
+        };
+
+        match outcome {
+            Ok(Outcome::Applied(_)) => {
+                state.remove(&record.title);
+                ctx.report.applied(&record.title, handler, "uninstalled");
+            }
+            Ok(Outcome::Skipped(reason)) => {
+                ctx.log.skip(format!("{}: {}", record.title, reason));
+                ctx.report.skipped(&record.title, handler, reason);
+            }
+            Err(mut e) => {
+                // Only what could not be reverted stays recorded.
+                if let InstallError::Partial { record: Some(remaining), .. } = &mut e {
+                    let remaining = *std::mem::take(remaining);
+                    state.upsert(Record {
+                        title: record.title.clone(),
+                        handler: record.handler.clone(),
+                        installed_at: record.installed_at,
+                        ..remaining
+                    });
+                }
+                report_failure(ctx, &record.title, handler, &e);
+                if ctx.fail_fast {
+                    skip_remaining(ctx, records[index + 1..].iter().map(|r| (&r.title, &r.handler)));
+                    break;
+                }
+            }
+        }
+    }
+
+    save_state(ctx, &state);
+    ctx.report.print_table();
+}
+
+/// Asks for the root password once when any of the `titles` runs commands as
+/// root. The returned guard keeps the credentials fresh until it is dropped;
+/// on failure those entries are reported as failed without running.
+fn authenticate(ctx: &Context, titles: &[&str]) -> Result<Option<Keepalive>, InstallError> {
+    if titles.is_empty() {
+        return Ok(None);
+    }
+    ctx.log.info(format!("Needs root ({}): {}", ctx.elevator.name(), titles.join(", ")));
+    ctx.elevator.authenticate()?;
+    Ok(ctx.elevator.keep_alive())
+}
+
+/// Stores the record of an applied entry in the install state.
+fn save_record(state: &mut State, choice: &FileEntry, mut record: Record) {
+    // Keep the packages, services and groups added by earlier runs:
+    // they are in place now, so this run does not report them again.
+    if let Some(previous) = state.get(&choice.title) {
+        merge(&mut record.packages, &previous.packages);
+        merge(&mut record.services, &previous.services);
+        merge(&mut record.user_groups, &previous.user_groups);
+    }
+    record.title = choice.title.clone();
+    record.handler = choice.handler.clone();
+    record.installed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    state.upsert(record);
+}
+
+/// Appends the values of `previous` missing from `values`.
+fn merge(values: &mut Vec<String>, previous: &[String]) {
+    for value in previous {
+        if !values.contains(value) {
+            values.push(value.clone());
+        }
+    }
+}
+
+fn report_failure(ctx: &Context, title: &str, handler: Option<&str>, error: &InstallError) {
+    ctx.log.error(format!("{}: {}", title, error));
+    ctx.report.failed(title, handler, error.kind(), error.to_string());
+}
+
+/// Reports the entries left out after a failure with `--fail-fast`.
+fn skip_remaining<'a>(ctx: &Context, remaining: impl Iterator<Item = (&'a String, &'a String)>) {
+    for (title, handler) in remaining {
+        ctx.report.skipped(title, Some(handler), "Not run because a previous entry failed (--fail-fast).");
+    }
+}
+
+/// Whether the changes of a record are still in place, if the handler can tell.
+fn is_present(record: &Record) -> Option<bool> {
+    match record.handler.as_str() {
+        "configs" => Some(configs::is_present(record)),
+        "assets" | "scripts" => Some(files_present(record)),
+        // A configuration that existed before is updated, not recorded.
+        "fastfetch" if !record.files.is_empty() => Some(files_present(record)),
+        _ => None,
+    }
+}
+
+fn load_state(ctx: &Context) -> State {
+    State::load().unwrap_or_else(|e| {
+        ctx.log.warn(format!("Failed to read install state, starting empty: {}", e));
+        State::default()
+    })
+}
+
+fn save_state(ctx: &Context, state: &State) {
+    if let Err(e) = state.save() {
+        ctx.log.error(format!("Failed to save install state: {}", e));
+    }
+}
+
+/// Catalog entries of every asset folder, for commands working with a single handler.
+pub(super) fn catalog(ctx: &Context) -> Vec<FileEntry> {
+    gather_asset_choices(ctx, &ASSET_FOLDERS)
+}
+
+fn gather_asset_choices(ctx: &Context, asset_folders: &[&str]) -> Vec<FileEntry> {
+    let mut asset_choices = Vec::new();
+
+    for folder in asset_folders {
+        let conf_file_path = ctx
+            .assets_dir
+            .join(folder)
+            .join(format!("{}.toml", folder))
+            .to_string_lossy()
+            .into_owned();
+        ctx.log.debug(format!("Checking existence for: {}", conf_file_path.bright_yellow()));
+        if Path::new(&conf_file_path).exists() {
+            ctx.log.debug(format!("Found configuration for: {}", conf_file_path.bright_yellow()));
             match fs::read_to_string(&conf_file_path) {
-                Ok(contents) => parse_config(folder, &contents, &mut asset_choices),
-                Err(e) => log_error_reading_config(folder, &e),
+                Ok(contents) => parse_config(ctx, folder, &contents, &mut asset_choices),
+                Err(e) => log_error_reading_config(ctx, folder, &e),
             }
         } else {
             asset_choices.push(FileEntry {
@@ -86,33 +355,38 @@ fn gather_asset_choices(asset_folders: &[&str]) -> Vec<FileEntry> {
                 default: false,
                 source_path: None,
                 target_path: None,
+                requires_root: false,
             });
         }
     }
 
-    if std::env::var("DEBUG").unwrap_or_default() == "true" {
-        println!();
-    }
-
     asset_choices
 }
 
-fn parse_config(folder: &&str, contents: &str, asset_choices: &mut Vec<FileEntry>) {
+fn parse_config(ctx: &Context, folder: &&str, contents: &str, asset_choices: &mut Vec<FileEntry>) {
     let config: Result<FileConfig, _> = toml::from_str(contents);
     match config {
         Ok(config) => {
             for mut file_entry in config.file {
                 let formatted_title = format!("{:50}", file_entry.title); // Ensure title is formatted to occupy 50 characters, padding with spaces if necessary
+                let root = if file_entry.requires_root { format!("{} ", "[root]".red()) } else { String::new() };
                 let display_text = format!(
-                    "{} ● {}",
-                    formatted_title, colorize_description(&file_entry.description)
+                    "{} ● {}{}",
+                    formatted_title, root, colorize_description(&file_entry.description)
                 );
                 // Replace '~/' with the actual user's home directory in target_path
+                // Resolve './assets/...' against the assets directory in use
+                file_entry.source_path = file_entry.source_path.map(|path| {
+                    match path.strip_prefix("./assets/").or_else(|| path.strip_prefix("assets/")) {
+                        Some(relative) => ctx.assets_dir.join(relative).to_string_lossy().into_owned(),
+                        None => path,
+                    }
+                });
                 file_entry.target_path = file_entry.target_path.map(|path| {
                     std::env::var("HOME").map(|home_dir| {
                         path.replace('~', &home_dir)
                     }).unwrap_or_else(|_| {
-                        eprintln!("{} Unable to retrieve HOME directory.", ":: Error:".red());
+                        ctx.log.error("Unable to retrieve HOME directory.");
                         path
                     })
                 });
@@ -123,30 +397,81 @@ fn parse_config(folder: &&str, contents: &str, asset_choices: &mut Vec<FileEntry
                     default: file_entry.default,
                     source_path: file_entry.source_path,
                     target_path: file_entry.target_path,
+                    requires_root: file_entry.requires_root,
                 });
             }
         }
         Err(e) => {
-            println!(
-                "{} Failed to parse TOML configuration for {}: {}",
-                ":: Error:".red(),
-                folder,
-                e
-            );
+            ctx.log.error(format!("Failed to parse TOML configuration for {}: {}", folder, e));
+        }
+    }
+}
+
+fn log_error_reading_config(ctx: &Context, folder: &&str, e: &std::io::Error) {
+    ctx.log.error(format!("Failed to read configuration file for {}: {}", folder, e));
+}
+
+/// Resolves a non-interactive selection to indices into `titles`.
+///
+/// Returns `None` when the user should be prompted instead, and an error
+/// when a `--select` title does not match any entry.
+fn select_titles(
+    titles: &[&str],
+    defaults: &[bool],
+    selection: &Selection,
+) -> Result<Option<Vec<usize>>, InstallError> {
+    match selection {
+        Selection::Prompt => Ok(None),
+        Selection::All => Ok(Some((0..titles.len()).collect())),
+        Selection::Defaults => Ok(Some((0..titles.len()).filter(|&i| defaults[i]).collect())),
+        Selection::Titles(queries) => {
+            let mut indices = Vec::new();
+            for query in queries {
+                match titles.iter().position(|title| title_matches(title, query)) {
+                    Some(index) if !indices.contains(&index) => indices.push(index),
+                    Some(_) => {}
+                    None => {
+                        return Err(InstallError::Selection(format!(
+                            "No entry matches '{}'. Available entries: {}",
+                            query,
+                            titles.join(", ")
+                        )));
+                    }
+                }
+            }
+            Ok(Some(indices))
         }
     }
 }
 
-fn log_error_reading_config(folder: &&str, e: &std::io::Error) {
-    eprintln!(
-        "{} Failed to read configuration file for {}: {}",
-        ":: Error:".red(),
-        folder,
-        e
-    );
+/// Matches a `--select` value against an entry title, ignoring case and the
+/// optional `[CATEGORY]` suffix (e.g. `monitors.conf` matches `Monitors.conf [CONFIG]`).
+fn title_matches(title: &str, query: &str) -> bool {
+    let query = query.trim();
+    title.eq_ignore_ascii_case(query)
+        || title
+            .split(" [")
+            .next()
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case(query))
 }
 
-fn make_choices(asset_choices: &[FileEntry]) -> Vec<FileEntry> {
+/// The entries to install: from the non-interactive `selection`, or picked
+/// in a prompt. Fails when nothing is selected.
+fn make_choices(
+    ctx: &Context,
+    asset_choices: &[FileEntry],
+    selection: &Selection,
+) -> Result<Vec<FileEntry>, InstallError> {
+    let selectable: Vec<&FileEntry> = asset_choices.iter().filter(|choice| !choice.handler.is_empty()).collect();
+    let titles: Vec<&str> = selectable.iter().map(|choice| choice.title.as_str()).collect();
+    let defaults: Vec<bool> = selectable.iter().map(|choice| choice.default).collect();
+    if let Some(indices) = select_titles(&titles, &defaults, selection)? {
+        if indices.is_empty() {
+            return Err(InstallError::Selection("Nothing was selected. Aborting installation.".to_string()));
+        }
+        return Ok(indices.into_iter().map(|i| selectable[i].clone()).collect());
+    }
+
     let mut categorized: Vec<(String, Vec<FileEntry>)> = Vec::new();
     let mut display_texts: Vec<String> = Vec::new();
     let mut defaults: Vec<bool> = Vec::new();
@@ -175,7 +500,7 @@ fn make_choices(asset_choices: &[FileEntry]) -> Vec<FileEntry> {
     }
 
     let selections = MultiSelect::with_theme(&ColorfulTheme::default())
-        .with_prompt(&format!(
+        .with_prompt(format!(
             "{}\n",
             "Select what to install:\n [a] All   [space] Toggle   [enter] Confirm".yellow()
         ))
@@ -183,35 +508,28 @@ fn make_choices(asset_choices: &[FileEntry]) -> Vec<FileEntry> {
         .defaults(&defaults)
         .interact_opt()
         .unwrap_or_else(|e| {
-            println!("{} Failed to select options: {}", ":: Error:".red(), e);
+            ctx.log.error(format!("Failed to select options: {}", e));
             None
         });
 
     if let Some(indices) = selections {
-        indices.iter()
+        Ok(indices.iter()
             .filter_map(|&i| {
-                if std::env::var("DEBUG").unwrap_or_default() == "true" {
-                    println!("{} Processing index: {}, display_text: {}", ":: Debug:".blue(), i, display_texts[i].bright_yellow());
-                }
+                ctx.log.debug(format!("Processing index: {}, display_text: {}", i, display_texts[i].bright_yellow()));
                 let found_choice = asset_choices.iter().find(|&choice| {
-                    if std::env::var("DEBUG").unwrap_or_default() == "true" {
-                        println!("{} Checking if choice title: {} matches display_text: {}", "  -> Debug:".blue(), choice.title.bright_yellow(), display_texts[i].bright_yellow());
-                    }
+                    ctx.log.trace(format!("Checking if choice title: {} matches display_text: {}", choice.title.bright_yellow(), display_texts[i].bright_yellow()));
                     display_texts[i].contains(&choice.title)
                 });
-                if std::env::var("DEBUG").unwrap_or_default() == "true" && found_choice.is_some() {
-                    println!("{} Match found for index: {}", "  -> Debug:".blue(), i);
+                if found_choice.is_some() {
+                    ctx.log.debug(format!("Match found for index: {}", i));
                 }
                 found_choice
             })
             .cloned()
-            .collect()
+            .collect())
     } else {
-        println!(
-            "\n{} Nothing was selected. Aborting installation.",
-            ":: Warning:".yellow()
-        );
-        std::process::exit(1);
+        println!();
+        Err(InstallError::Selection("Nothing was selected. Aborting installation.".to_string()))
     }
 }
 
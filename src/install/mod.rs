@@ -1,12 +1,23 @@
 use serde::Deserialize;
+use std::{fs, path::Path};
+
+use crate::context::Context;
+use crate::state::Record;
 
 mod configs;
+mod error;
 mod fastfetch;
 mod packages;
 mod scripts;
+mod sync;
 mod ufw;
 pub mod manager;
 
+pub(crate) use error::{command_output, describe, run_command, InstallError};
+pub use packages::{diff_packages, export_packages, DiffOptions, ExportOptions, PackageManager};
+pub use fastfetch::{preview_images, Preview};
+pub use ufw::switch_profile as switch_firewall_profile;
+
 #[derive(Deserialize, Debug)]
 struct FileConfig {
     file: Vec<FileEntry>,
@@ -23,4 +34,50 @@ struct FileEntry {
     source_path: Option<String>,
     #[serde(default)]
     target_path: Option<String>,
-}
\ No newline at end of file
+    /// The handler runs commands as root; shown in the picker and
+    /// authenticated once before the first entry runs.
+    #[serde(default)]
+    requires_root: bool,
+}
+
+/// Result of a handler that did not fail for a single catalog entry.
+pub(crate) enum Outcome {
+    /// The entry was applied; the record describes what was changed.
+    Applied(Box<Record>),
+    /// Nothing was changed, with the reason why.
+    Skipped(String),
+}
+
+/// Removes the files recorded by a handler that copies files (`assets`, `scripts`).
+fn remove_files(ctx: &Context, record: &Record) -> Result<Outcome, InstallError> {
+    let mut failures = Vec::new();
+    for file in &record.files {
+        match fs::remove_file(file) {
+            Ok(()) => ctx.log.debug(format!("Removed {}", file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                ctx.log.debug(format!("Already removed: {}", file))
+            }
+            Err(e) => failures.push(format!("{}: {}", file, e)),
+        }
+    }
+
+    if failures.is_empty() {
+        ctx.log.success(format!("removed {} file(s)", record.files.len()));
+        Ok(Outcome::Applied(Box::new(record.clone())))
+    } else {
+        Err(InstallError::Partial { failed: failures, record: None })
+    }
+}
+
+/// Whether every file recorded by a handler still exists.
+fn files_present(record: &Record) -> bool {
+    !record.files.is_empty() && record.files.iter().all(|file| Path::new(file).exists())
+}
+
+/// `path` with a leading `~/` replaced by `$HOME`.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+use crate::install::{command_output, InstallError};
+use crate::privilege::{on_path, Elevator};
+
+use super::list::Source;
+
+/// Tool used to install packages. AUR helpers install both repository and
+/// AUR packages, `pacman` only the official repositories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Yay,
+    Paru,
+    Pikaur,
+    Pacman,
+}
+
+impl PackageManager {
+    /// Accepted values of `--package-manager` and `AURHELPER`.
+    pub const NAMES: [&'static str; 4] = ["yay", "paru", "pikaur", "pacman"];
+
+    /// AUR helpers in order of preference for detection.
+    const HELPERS: [PackageManager; 3] = [PackageManager::Yay, PackageManager::Paru, PackageManager::Pikaur];
+
+    pub fn from_name(name: &str) -> Option<PackageManager> {
+        match name {
+            "yay" => Some(PackageManager::Yay),
+            "paru" => Some(PackageManager::Paru),
+            "pikaur" => Some(PackageManager::Pikaur),
+            "pacman" => Some(PackageManager::Pacman),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PackageManager::Yay => "yay",
+            PackageManager::Paru => "paru",
+            PackageManager::Pikaur => "pikaur",
+            PackageManager::Pacman => "pacman",
+        }
+    }
+
+    /// `AURHELPER` when it names a known backend, otherwise the first AUR
+    /// helper found on `PATH`, falling back to plain `pacman`.
+    pub fn detect() -> PackageManager {
+        if let Some(manager) = std::env::var("AURHELPER").ok().and_then(|name| PackageManager::from_name(name.trim())) {
+            return manager;
+        }
+        PackageManager::HELPERS
+            .into_iter()
+            .find(|helper| on_path(helper.name()))
+            .unwrap_or(PackageManager::Pacman)
+    }
+
+    pub fn supports_aur(self) -> bool {
+        self != PackageManager::Pacman
+    }
+
+    /// Command printing repository (and AUR, for helpers) information of `packages`.
+    pub fn info_command(self, packages: &[&str]) -> Command {
+        let mut command = Command::new(self.name());
+        command.arg("-Si").args(packages);
+        command
+    }
+
+    /// Command installing `packages` without reinstalling up-to-date ones,
+    /// without confirmation and without the interactive review steps of the helper.
+    pub fn install_command(self, elevator: Elevator, packages: &[&str]) -> Command {
+        let mut command = match self {
+            // pacman has to be run as root, the helpers elevate themselves.
+            PackageManager::Pacman => elevator.command("pacman"),
+            helper => Command::new(helper.name()),
+        };
+        // yay and paru call sudo unless told otherwise.
+        if matches!(self, PackageManager::Yay | PackageManager::Paru) && elevator != Elevator::Sudo {
+            command.args(["--sudo", elevator.name()]);
+        }
+        match self {
+            PackageManager::Yay => command.args(["--answerclean", "None", "--answerdiff", "None"]),
+            PackageManager::Paru => command.arg("--skipreview"),
+            PackageManager::Pikaur => command.args(["--noedit", "--nodiff"]),
+            PackageManager::Pacman => &mut command,
+        };
+        // Packages were already chosen in hyde-ext and the output is piped,
+        // so the transaction must not stop at a confirmation prompt.
+        command.args(["-S", "--needed", "--noconfirm"]).args(packages);
+        command
+    }
+}
+
+/// Installed packages as qualified names (see [`super::list::Package::qualified_name`]):
+/// pacman packages, plus flatpak applications and cargo crates when `sources`
+/// include them.
+pub(super) fn installed(sources: &[Source]) -> Result<HashSet<String>, InstallError> {
+    let mut installed: HashSet<String> = query_lines(Command::new("pacman").arg("-Qq"))?.into_iter().collect();
+    if sources.contains(&Source::Flatpak) {
+        let applications = query_lines(Command::new("flatpak").args(["list", "--app", "--columns=application"]))?;
+        installed.extend(applications.into_iter().map(|id| format!("flatpak:{}", id)));
+    }
+    if sources.contains(&Source::Cargo) {
+        // `cargo install --list` prints `name v1.0.0:` followed by indented binaries.
+        let crates = query_lines(Command::new("cargo").args(["install", "--list"]))?;
+        installed.extend(
+            crates
+                .iter()
+                .filter(|line| !line.starts_with(char::is_whitespace))
+                .filter_map(|line| line.split_whitespace().next())
+                .map(|name| format!("cargo:{}", name)),
+        );
+    }
+    Ok(installed)
+}
+
+/// Installed pacman packages with their version (`pacman -Q`).
+pub(super) fn versions() -> Result<HashMap<String, String>, InstallError> {
+    Ok(query_lines(Command::new("pacman").arg("-Q"))?
+        .iter()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, version)| (name.to_string(), version.trim().to_string()))
+        .collect())
+}
+
+fn query_lines(command: &mut Command) -> Result<Vec<String>, InstallError> {
+    Ok(command_output(command)?.lines().map(str::to_string).collect())
+}
+
+/// Per-user Flathub install of `applications`, without prompts.
+pub(super) fn flatpak_install_command(applications: &[&str]) -> Command {
+    let mut command = Command::new("flatpak");
+    command.args(["install", "--user", "--noninteractive", "flathub"]).args(applications);
+    command
+}
+
+pub(super) fn flatpak_remove_command(applications: &[&str]) -> Command {
+    let mut command = Command::new("flatpak");
+    command.args(["uninstall", "--user", "--noninteractive"]).args(applications);
+    command
+}
+
+pub(super) fn cargo_install_command(crates: &[&str]) -> Command {
+    let mut command = Command::new("cargo");
+    command.arg("install").args(crates);
+    command
+}
+
+pub(super) fn cargo_remove_command(crates: &[&str]) -> Command {
+    let mut command = Command::new("cargo");
+    command.arg("uninstall").args(crates);
+    command
+}
+
+/// `pacman -Rns` as root: removes `packages` with their unneeded dependencies
+/// and configuration files.
+pub(super) fn remove_command(elevator: Elevator, packages: &[&str]) -> Command {
+    let mut command = elevator.command("pacman");
+    command.arg("-Rns").args(packages);
+    command
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::install::expand_home;
+use crate::privilege::on_path;
+
+/// `when` condition of a manifest package. Every field that is set must hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Condition {
+    /// GPU vendor reported by `lspci`: `amd`, `nvidia` or `intel`.
+    pub gpu: Option<String>,
+    /// Command that has to be available on `PATH`.
+    pub command: Option<String>,
+    /// Path that has to exist (`~` is expanded).
+    pub file: Option<String>,
+}
+
+/// Facts about the machine, gathered once per run.
+pub(crate) struct System {
+    gpu_vendors: Vec<&'static str>,
+}
+
+impl System {
+    pub fn detect() -> System {
+        System { gpu_vendors: gpu_vendors() }
+    }
+
+    pub fn satisfies(&self, condition: &Condition) -> bool {
+        let gpu = condition
+            .gpu
+            .as_ref()
+            .is_none_or(|vendor| self.gpu_vendors.iter().any(|v| v.eq_ignore_ascii_case(vendor)));
+        let command = condition.command.as_ref().is_none_or(|name| on_path(name));
+        let file = condition.file.as_ref().is_none_or(|path| Path::new(&expand_home(path)).exists());
+        gpu && command && file
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(gpu) = &self.gpu {
+            parts.push(format!("gpu = {}", gpu));
+        }
+        if let Some(command) = &self.command {
+            parts.push(format!("command = {}", command));
+        }
+        if let Some(file) = &self.file {
+            parts.push(format!("file = {}", file));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Vendors of the display controllers listed by `lspci`.
+fn gpu_vendors() -> Vec<&'static str> {
+    let output = match Command::new("lspci").output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => return Vec::new(),
+    };
+
+    let mut vendors = Vec::new();
+    for line in output.lines().filter(|line| {
+        line.contains("VGA compatible controller") || line.contains("3D controller") || line.contains("Display controller")
+    }) {
+        let vendor = if line.contains("AMD") || line.contains("ATI") {
+            "amd"
+        } else if line.contains("NVIDIA") {
+            "nvidia"
+        } else if line.contains("Intel") {
+            "intel"
+        } else {
+            continue;
+        };
+        if !vendors.contains(&vendor) {
+            vendors.push(vendor);
+        }
+    }
+    vendors
+}
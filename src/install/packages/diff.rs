@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::context::Context;
+use crate::install::{manager, InstallError};
+
+use super::list::{Package, PackageGroup, Source};
+use super::{applicable, backend, export, parse_groups, sources};
+
+/// Prefix of the pseudo groups holding the explicitly installed packages that
+/// are not listed, one per repository.
+const EXTRA_GROUP: &str = "Not in list";
+
+/// Options of `hyde-ext packages diff`.
+pub struct DiffOptions {
+    /// List to compare with, the package list of the catalog when `None`.
+    pub against: Option<PathBuf>,
+    /// Package lists left out of the extra packages instead of the HyDE core list.
+    pub base: Vec<PathBuf>,
+}
+
+/// Drift of one group of the list.
+#[derive(Debug, Default)]
+struct GroupDiff {
+    name: String,
+    missing: Vec<String>,
+    /// `(package, listed version, installed version)`
+    mismatched: Vec<(String, String, String)>,
+    extra: Vec<String>,
+}
+
+impl GroupDiff {
+    fn in_sync(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty() && self.extra.is_empty()
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.missing.is_empty() {
+            lines.push(format!("missing: {}", self.missing.join(", ")));
+        }
+        for (name, listed, installed) in &self.mismatched {
+            lines.push(format!("version: {} {} installed, {} listed", name, installed, listed));
+        }
+        if !self.extra.is_empty() {
+            lines.push(format!("extra: {}", self.extra.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Compares the installed packages with a package list and reports, per
+/// group, the default packages that are missing, the packages whose installed
+/// version differs from the listed one and the explicitly installed packages
+/// that are not listed at all.
+pub fn diff_packages(ctx: &Context, options: &DiffOptions) {
+    let diffs = match diff(ctx, options) {
+        Ok(diffs) => diffs,
+        Err(e) => {
+            ctx.log.error(&e);
+            ctx.report.failed("packages", Some("packages"), e.kind(), e.to_string());
+            return;
+        }
+    };
+
+    for diff in &diffs {
+        ctx.log.heading(format!("===== {} =====", diff.name));
+        let lines = diff.lines();
+        if lines.is_empty() {
+            ctx.log.success("in sync");
+        }
+        for line in &lines {
+            ctx.log.detail(line);
+        }
+        let message = if lines.is_empty() { "in sync".to_string() } else { lines.join("; ") };
+        ctx.report.status(&diff.name, Some("packages"), diff.in_sync(), message);
+    }
+
+    let count = |field: fn(&GroupDiff) -> usize| diffs.iter().map(field).sum::<usize>();
+    let (missing, mismatched, extra) = (
+        count(|diff| diff.missing.len()),
+        count(|diff| diff.mismatched.len()),
+        count(|diff| diff.extra.len()),
+    );
+    if missing + mismatched + extra == 0 {
+        ctx.log.success("installed packages match the list");
+    } else {
+        ctx.log.warn(format!(
+            "{} missing, {} extra and {} version-mismatched package(s).",
+            missing, extra, mismatched
+        ));
+    }
+}
+
+fn diff(ctx: &Context, options: &DiffOptions) -> Result<Vec<GroupDiff>, InstallError> {
+    let path = match &options.against {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => manager::catalog(ctx)
+            .into_iter()
+            .find(|entry| entry.handler == "packages")
+            .and_then(|entry| entry.source_path)
+            .ok_or_else(|| {
+                InstallError::MissingPath("No package list found in the catalog, use --against.".to_string())
+            })?,
+    };
+    ctx.log.action("Comparing", format!("installed packages with {}", path));
+    let content = fs::read_to_string(&path).map_err(|e| InstallError::io(format!("Failed to read {}", path), e))?;
+
+    let groups = parse_groups(&path, &content)?;
+    // Every listed package counts as expected, including suggestions and
+    // packages whose condition does not hold here.
+    let listed: HashSet<String> =
+        groups.iter().flat_map(|group| &group.packages).map(Package::qualified_name).collect();
+    let groups: Vec<PackageGroup> = applicable(ctx, groups);
+
+    let packages = groups.iter().flat_map(|group| &group.packages);
+    let installed = backend::installed(&sources(packages.filter_map(|package| package.source)))?;
+    let versions = backend::versions()?;
+    let mut diffs: Vec<GroupDiff> = groups
+        .iter()
+        .map(|group| {
+            let mut diff = GroupDiff { name: group.name.clone(), ..GroupDiff::default() };
+            for package in &group.packages {
+                let name = package.qualified_name();
+                if !installed.contains(&name) {
+                    if !package.suggested {
+                        diff.missing.push(name);
+                    }
+                    continue;
+                }
+                // Versions are those of `pacman -Q`, which does not know flatpak and cargo packages.
+                if package.source.is_some_and(Source::is_external) {
+                    continue;
+                }
+                if let (Some(listed), Some(installed)) = (&package.version, versions.get(&package.name)) {
+                    if !version_matches(listed, installed) {
+                        diff.mismatched.push((name, listed.clone(), installed.clone()));
+                    }
+                }
+            }
+            diff
+        })
+        .collect();
+
+    let base = export::base_packages(ctx, &options.base)?;
+    let mut extra: Vec<String> = export::query_installed("-Qqe")?
+        .into_iter()
+        .filter(|name| !listed.contains(name) && !base.contains(name))
+        .collect();
+    extra.sort();
+    // Grouped like `packages export` would write them.
+    for (group, extra) in export::group_packages(&extra, &[])? {
+        diffs.push(GroupDiff { name: format!("{} ({})", EXTRA_GROUP, group), extra, ..GroupDiff::default() });
+    }
+    Ok(diffs)
+}
+
+/// A listed version matches when it equals the installed one, or when it
+/// leaves out the release (`7.95` matches `7.95-1`).
+fn version_matches(listed: &str, installed: &str) -> bool {
+    installed == listed || installed.strip_prefix(listed).is_some_and(|release| release.starts_with('-'))
+}
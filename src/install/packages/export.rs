@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::context::Context;
+use crate::install::{describe, InstallError};
+
+use super::backend::{self, PackageManager};
+use super::resolve;
+
+/// Package list HyDE installs itself, subtracted from the export when present.
+const HYDE_CORE_LIST: &str = "HyDE/Scripts/pkg_core.lst";
+
+/// Repositories in the order their groups are written; others follow, AUR last.
+const REPOSITORY_ORDER: [&str; 3] = ["core", "extra", "multilib"];
+
+/// Options of `hyde-ext packages export`.
+pub struct ExportOptions {
+    /// File the list is written to, stdout when `None`.
+    pub file: Option<PathBuf>,
+    /// Package lists to subtract instead of the HyDE core list.
+    pub base: Vec<PathBuf>,
+    /// TOML file with `[[rule]]` tables assigning packages to groups.
+    pub rules: Option<PathBuf>,
+    /// Writes the installed version after every package name.
+    pub versions: bool,
+}
+
+/// User-defined grouping: packages whose name matches `pattern` go to `group`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    group: String,
+    pattern: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rules {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+/// Writes the explicitly installed packages of this machine, minus the HyDE
+/// base set, as a `packages.list`.
+pub fn export_packages(ctx: &Context, options: &ExportOptions) {
+    let entry = options
+        .file
+        .as_ref()
+        .map_or_else(|| "stdout".to_string(), |file| file.display().to_string());
+    ctx.report.started(&entry, None);
+
+    match export(ctx, options) {
+        Ok((packages, groups, contents)) => {
+            let message = format!("exported {} packages in {} groups", packages, groups);
+            match contents {
+                // The list is part of the report so that stdout stays valid JSON.
+                Some(contents) if ctx.report.format().is_machine() => {
+                    ctx.report.applied_with_output(&entry, None, message, contents)
+                }
+                Some(contents) => {
+                    print!("{}", contents);
+                    ctx.report.applied(&entry, None, message);
+                }
+                None => ctx.report.applied(&entry, None, message),
+            }
+        }
+        Err(e) => {
+            ctx.log.error(&e);
+            ctx.report.failed(&entry, None, e.kind(), e.to_string());
+        }
+    }
+    // The list itself goes to stdout when no file is given.
+    if options.file.is_some() {
+        ctx.report.print_table();
+    }
+}
+
+/// Exports the list; returns the number of packages and groups, and the list
+/// itself when no file is given.
+fn export(ctx: &Context, options: &ExportOptions) -> Result<(usize, usize, Option<String>), InstallError> {
+    if let Some(file) = &options.file {
+        if file.exists() && !ctx.force {
+            return Err(InstallError::Conflict(format!(
+                "{} already exists, use --force to overwrite it.",
+                file.display()
+            )));
+        }
+    }
+
+    let rules = match &options.rules {
+        Some(path) => load_rules(path)?,
+        None => Vec::new(),
+    };
+    let base = base_packages(ctx, &options.base)?;
+
+    let explicit: Vec<String> = query_installed("-Qqe")?
+        .into_iter()
+        .filter(|name| !base.contains(name))
+        .collect();
+    ctx.log.debug(format!("{} explicitly installed packages outside the base set", explicit.len()));
+    let groups = group_packages(&explicit, &rules)?;
+
+    let versions = if options.versions { backend::versions()? } else { HashMap::new() };
+    let contents = render(&groups, &versions);
+    let total = groups.iter().map(|(_, packages)| packages.len()).sum();
+    match &options.file {
+        Some(file) => {
+            if let Some(parent) = file.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .map_err(|e| InstallError::io(format!("Failed to create {}", parent.display()), e))?;
+            }
+            fs::write(file, contents).map_err(|e| InstallError::io(format!("Failed to write {}", file.display()), e))?;
+            ctx.log.success(format!("exported packages to {}", file.display()));
+            Ok((total, groups.len(), None))
+        }
+        None => Ok((total, groups.len(), Some(contents))),
+    }
+}
+
+/// Groups `packages` by the first rule matching them, then by the repository
+/// `pacman -Si` finds them in, with foreign packages in `AUR`; in the order
+/// the groups are written, without empty ones.
+pub(super) fn group_packages(
+    packages: &[String],
+    rules: &[(String, Regex)],
+) -> Result<Vec<(String, Vec<String>)>, InstallError> {
+    let foreign: HashSet<String> = query_installed("-Qqm")?.into_iter().collect();
+    let native: Vec<&str> = packages
+        .iter()
+        .filter(|name| !foreign.contains(*name))
+        .map(String::as_str)
+        .collect();
+    let info = resolve::query(PackageManager::Pacman, &native)?;
+
+    let mut groups: Vec<(String, Vec<String>)> = rules.iter().map(|(rule, _)| (rule.clone(), Vec::new())).collect();
+    for name in packages {
+        let group = match rules.iter().find(|(_, pattern)| pattern.is_match(name)) {
+            Some((group, _)) => group.clone(),
+            None if foreign.contains(name) => "AUR".to_string(),
+            None => match info.get(name).and_then(|info| info.repository.as_deref()) {
+                Some(repository) => title_case(repository),
+                None => "Other".to_string(),
+            },
+        };
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, packages)) => packages.push(name.clone()),
+            None => groups.push((group, vec![name.clone()])),
+        }
+    }
+    groups.retain(|(_, packages)| !packages.is_empty());
+    groups.sort_by_key(|(name, _)| group_rank(name, rules));
+    Ok(groups)
+}
+
+/// Rule groups first (in file order), then repositories, then AUR.
+fn group_rank(group: &str, rules: &[(String, Regex)]) -> (usize, usize) {
+    if let Some(index) = rules.iter().position(|(name, _)| name == group) {
+        return (0, index);
+    }
+    match group {
+        "AUR" => (3, 0),
+        "Other" => (2, 1),
+        _ => match REPOSITORY_ORDER.iter().position(|repository| title_case(repository) == group) {
+            Some(index) => (1, index),
+            None => (2, 0),
+        },
+    }
+}
+
+/// The `packages.list` format read by the packages handler, with `name
+/// version` lines for the packages found in `versions`.
+fn render(groups: &[(String, Vec<String>)], versions: &HashMap<String, String>) -> String {
+    groups
+        .iter()
+        .map(|(name, packages)| {
+            let lines: Vec<String> = packages
+                .iter()
+                .map(|package| match versions.get(package) {
+                    Some(version) => format!("{} {}", package, version),
+                    None => package.clone(),
+                })
+                .collect();
+            format!("# ========= {} =========\n{}\n", name, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn load_rules(path: &Path) -> Result<Vec<(String, Regex)>, InstallError> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| InstallError::io(format!("Failed to read {}", path.display()), e))?;
+    let rules: Rules = toml::from_str(&contents)
+        .map_err(|e| InstallError::Parse(format!("Invalid export rules {}: {}", path.display(), e.message())))?;
+    rules
+        .rules
+        .into_iter()
+        .map(|rule| match Regex::new(&rule.pattern) {
+            Ok(pattern) => Ok((rule.group, pattern)),
+            Err(e) => Err(InstallError::Parse(format!("Invalid pattern '{}': {}", rule.pattern, e))),
+        })
+        .collect()
+}
+
+/// Packages of the base lists, or of HyDE's core list when none are given.
+/// Lines may carry `#` comments and `package|dependency` suffixes.
+pub(super) fn base_packages(ctx: &Context, lists: &[PathBuf]) -> Result<HashSet<String>, InstallError> {
+    let mut lists = lists.to_vec();
+    if lists.is_empty() {
+        let core = PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(HYDE_CORE_LIST);
+        if core.exists() {
+            lists.push(core);
+        } else {
+            ctx.log.warn(format!("{} not found, the HyDE base set is not subtracted.", core.display()));
+        }
+    }
+
+    let mut packages = HashSet::new();
+    for list in &lists {
+        let contents =
+            fs::read_to_string(list).map_err(|e| InstallError::io(format!("Failed to read {}", list.display()), e))?;
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            if let Some(name) = line.split(['|', ' ', '\t']).map(str::trim).find(|token| !token.is_empty()) {
+                packages.insert(name.to_string());
+            }
+        }
+    }
+    Ok(packages)
+}
+
+pub(super) fn query_installed(flags: &str) -> Result<Vec<String>, InstallError> {
+    let mut command = Command::new("pacman");
+    command.arg(flags);
+    let output = command.output().map_err(|e| InstallError::not_started(describe(&command), e))?;
+    // `-Qqm` exits with 1 and prints nothing when there are no foreign packages.
+    if !output.status.success() && !output.stderr.is_empty() {
+        return Err(InstallError::failed(describe(&command), output.status, &String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+fn title_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,149 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use super::condition::Condition;
+
+/// A `# ===== Group =====` section of a `packages.list`, or a `[[group]]` of a
+/// package manifest.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PackageGroup {
+    pub name: String,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Package {
+    pub name: String,
+    /// Commented out in the list (e.g. `#obs-studio`) or `optional = true` in
+    /// a manifest: offered, but not selected by default.
+    #[serde(default, rename = "optional")]
+    pub suggested: bool,
+    /// Where the package comes from; `None` lets the package manager decide.
+    #[serde(default)]
+    pub source: Option<Source>,
+    /// Version installed on the reference machine, only compared by `packages diff`.
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Only offered when the condition holds on this machine.
+    #[serde(default)]
+    pub when: Option<Condition>,
+    /// Shell commands run after the package was installed.
+    #[serde(default)]
+    pub post_install: Vec<String>,
+    /// System services enabled and started after installing.
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// User services enabled and started with `systemctl --user`.
+    #[serde(default)]
+    pub user_services: Vec<String>,
+    /// Groups the current user is added to.
+    #[serde(default)]
+    pub user_groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Source {
+    /// Official repositories, installed with pacman.
+    Repo,
+    /// Arch User Repository, installed with an AUR helper.
+    Aur,
+    /// Flatpak application from Flathub (`flatpak:` prefix).
+    Flatpak,
+    /// Crate installed with `cargo install` (`cargo:` prefix).
+    Cargo,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Repo => "repo",
+            Source::Aur => "aur",
+            Source::Flatpak => "flatpak",
+            Source::Cargo => "cargo",
+        }
+    }
+
+    /// Sources installed with their own tool instead of pacman or an AUR helper.
+    pub fn is_external(self) -> bool {
+        matches!(self, Source::Flatpak | Source::Cargo)
+    }
+}
+
+impl Package {
+    /// Name as written in package lists and install records: external
+    /// packages keep their `flatpak:` or `cargo:` prefix.
+    pub fn qualified_name(&self) -> String {
+        match self.source {
+            Some(source) if source.is_external() => format!("{}:{}", source.name(), self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Moves a `flatpak:` or `cargo:` prefix of the name into `source`.
+    pub fn normalize(&mut self) {
+        if let (Some(source), name) = split_prefix(&self.name) {
+            self.name = name.to_string();
+            self.source = Some(source);
+        }
+    }
+}
+
+/// Splits `flatpak:<id>` and `cargo:<crate>` into their source and name.
+pub(crate) fn split_prefix(entry: &str) -> (Option<Source>, &str) {
+    if let Some(name) = entry.strip_prefix("flatpak:") {
+        (Some(Source::Flatpak), name)
+    } else if let Some(name) = entry.strip_prefix("cargo:") {
+        (Some(Source::Cargo), name)
+    } else {
+        (None, entry)
+    }
+}
+
+/// Parses the legacy `packages.list` format: group headers are comment lines
+/// containing `=====`, every other non-empty line is a package (optionally
+/// prefixed with `flatpak:` or `cargo:`, followed by a version and an inline
+/// `#` comment) and commented out package names are kept as suggestions.
+pub(crate) fn parse(content: &str) -> Vec<PackageGroup> {
+    let package_name = Regex::new(r"^((flatpak|cargo):)?[A-Za-z0-9@._+-]+$").unwrap();
+    let package_version = Regex::new(r"^[A-Za-z0-9.:_+~-]*[0-9][A-Za-z0-9.:_+~-]*$").unwrap();
+    let mut groups: Vec<PackageGroup> = Vec::new();
+    let mut current = PackageGroup { name: String::new(), packages: Vec::new() };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            if line.contains("=====") {
+                if !current.packages.is_empty() {
+                    groups.push(current);
+                }
+                current = PackageGroup {
+                    name: line.trim_matches(|c: char| c == '#' || c == '=' || c.is_whitespace()).to_string(),
+                    packages: Vec::new(),
+                };
+            } else if package_name.is_match(comment.trim()) {
+                let mut package = Package { name: comment.trim().to_string(), suggested: true, ..Package::default() };
+                package.normalize();
+                current.packages.push(package);
+            }
+        } else {
+            // `name [version] [# comment]`; a second word that is not a version is ignored.
+            let mut words = line.split('#').next().unwrap_or_default().split_whitespace();
+            let Some(name) = words.next() else { continue };
+            let version = words.next().filter(|version| package_version.is_match(version)).map(str::to_string);
+            let mut package = Package { name: name.to_string(), version, ..Package::default() };
+            package.normalize();
+            current.packages.push(package);
+        }
+    }
+
+    if !current.packages.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
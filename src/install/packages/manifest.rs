@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+use super::list::PackageGroup;
+
+/// TOML package manifest, the structured alternative to `packages.list`:
+///
+/// ```toml
+/// [[group]]
+/// name = "Bootloader and Drivers"
+///
+/// [[group.package]]
+/// name = "opencl-amd"
+/// source = "aur"                  # "repo", "aur", "flatpak" or "cargo"
+/// description = "OpenCL runtime for AMD GPUs"
+/// when = { gpu = "amd" }
+/// post_install = ["echo done"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default, rename = "group")]
+    groups: Vec<PackageGroup>,
+}
+
+pub(crate) fn parse(content: &str) -> Result<Vec<PackageGroup>, toml::de::Error> {
+    let mut groups = toml::from_str::<Manifest>(content)?.groups;
+    for package in groups.iter_mut().flat_map(|group| &mut group.packages) {
+        package.normalize();
+    }
+    Ok(groups)
+}
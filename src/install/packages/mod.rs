@@ -0,0 +1,483 @@
+mod backend;
+mod condition;
+mod diff;
+mod export;
+mod list;
+mod manifest;
+mod progress;
+mod resolve;
+mod setup;
+
+use std::{fs, io::{self, IsTerminal}, path::Path, process::Command};
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+
+use crate::context::Context;
+use crate::install::{FileEntry, InstallError, Outcome};
+use crate::state::Record;
+
+pub use backend::PackageManager;
+pub use diff::{diff_packages, DiffOptions};
+pub use export::{export_packages, ExportOptions};
+use condition::System;
+use list::{Package, PackageGroup, Source};
+use resolve::Resolution;
+
+pub(crate) fn install(ctx: &Context, choice: &FileEntry) -> Result<Outcome, InstallError> {
+    let source_path = match &choice.source_path {
+        Some(path) => path,
+        None => return Err(InstallError::MissingPath("Source path for packages is missing.".to_string())),
+    };
+
+    let content = match fs::read_to_string(source_path) {
+        Ok(content) => content,
+        Err(e) => {
+            return Err(InstallError::io(format!("Failed to read package list file {}", source_path), e));
+        }
+    };
+
+    let manager = ctx.packages.manager.unwrap_or_else(PackageManager::detect);
+    ctx.log.debug(format!("Installing packages with {}.", manager.name()));
+
+    let groups = parse_groups(source_path, &content)?;
+    let mut groups = select_packages(ctx, applicable(ctx, groups))?;
+    if groups.is_empty() {
+        return Ok(Outcome::Skipped("No packages were selected.".to_string()));
+    }
+
+    ctx.log.action("Resolving", format!("{} packages", groups.iter().map(|group| group.packages.len()).sum::<usize>()));
+    let sources = sources(groups.iter().flat_map(|group| &group.packages).filter_map(|package| package.source));
+    let before = backend::installed(&sources)?;
+    let resolutions = resolve::resolve(manager, &before, &mut groups)?;
+
+    let not_installed = install_transactions(ctx, manager, &groups);
+
+    let mut failed = Vec::new();
+    let mut installed = 0;
+    let mut changes = setup::Changes::default();
+    for (group, resolution) in groups.iter().zip(&resolutions) {
+        ctx.log.heading(format!("===== {} =====", group.name));
+        let missing: Vec<String> = group
+            .packages
+            .iter()
+            .map(Package::qualified_name)
+            .filter(|name| not_installed.contains(name))
+            .collect();
+        let result = if missing.is_empty() {
+            // Packages that were installed already may not be set up yet.
+            run_post_install(ctx, &group.packages)
+                .and_then(|()| setup::apply(ctx, group.packages.iter().chain(&resolution.present), &mut changes))
+        } else {
+            Err(InstallError::Partial { failed: missing, record: None })
+        };
+        print_group_summary(ctx, group, resolution, result.is_ok());
+        match result {
+            Ok(()) => installed += group.packages.len(),
+            Err(e) => {
+                ctx.log.error(format!("{}: {}", group.name, e));
+                failed.push(group.name.clone());
+                if ctx.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    setup::print_relogin_summary(ctx, &changes);
+    let set_up = !changes.services.is_empty() || !changes.user_groups.is_empty();
+    if installed == 0 && !set_up && failed.is_empty() {
+        let skipped = resolutions.iter().map(|resolution| resolution.skipped.len()).sum::<usize>();
+        return Ok(Outcome::Skipped(format!("Nothing to install, {} packages were skipped.", skipped)));
+    }
+
+    // Only packages that are present now and were missing before were added
+    // by this run; `--needed` leaves the others untouched. Groups that failed
+    // may still have added some.
+    let after = backend::installed(&sources)?;
+    let packages = groups
+        .iter()
+        .flat_map(|group| &group.packages)
+        .map(Package::qualified_name)
+        .filter(|name| after.contains(name) && !before.contains(name))
+        .collect();
+    let record = Box::new(Record {
+        packages,
+        services: changes.services,
+        user_groups: changes.user_groups,
+        ..Record::default()
+    });
+    if !failed.is_empty() {
+        return Err(InstallError::Partial { failed, record: Some(record) });
+    }
+    Ok(Outcome::Applied(record))
+}
+
+/// Disables the recorded services, leaves the recorded groups and removes the
+/// recorded packages that are still installed: pacman packages with
+/// `pacman -Rns`, flatpak applications and cargo crates with their tool.
+pub(crate) fn uninstall(ctx: &Context, record: &Record) -> Result<Outcome, InstallError> {
+    // What could not be reverted stays recorded, like a partial install.
+    let setup::Changes { services, user_groups } = setup::revert(ctx, record);
+    let mut failed: Vec<String> = services.iter().chain(&user_groups).cloned().collect();
+    let partial = |failed: Vec<String>, packages: Vec<String>| InstallError::Partial {
+        failed,
+        record: Some(Box::new(Record {
+            packages,
+            services: services.clone(),
+            user_groups: user_groups.clone(),
+            ..Record::default()
+        })),
+    };
+
+    let installed = match backend::installed(&record_sources(record)) {
+        Ok(installed) => installed,
+        Err(e) if failed.is_empty() => return Err(e),
+        Err(e) => {
+            ctx.log.error(&e);
+            return Err(partial(failed, record.packages.clone()));
+        }
+    };
+    let present: Vec<&String> = record.packages.iter().filter(|name| installed.contains(*name)).collect();
+    if present.is_empty() {
+        ctx.log.info("None of the packages installed by hyde-ext are present anymore.");
+        if !failed.is_empty() {
+            return Err(partial(failed, Vec::new()));
+        }
+        return Ok(Outcome::Applied(Box::new(record.clone())));
+    }
+
+    let names = |source: Option<Source>| -> Vec<&str> {
+        present
+            .iter()
+            .map(|entry| list::split_prefix(entry))
+            .filter(|(entry_source, _)| *entry_source == source)
+            .map(|(_, name)| name)
+            .collect()
+    };
+    let (pacman, flatpak, cargo) = (names(None), names(Some(Source::Flatpak)), names(Some(Source::Cargo)));
+
+    ctx.log.action("Removing", format!("packages: {:?}", present));
+    let batches = [
+        (None, "pacman -Rns", backend::remove_command(ctx.elevator, &pacman), &pacman),
+        (Some(Source::Flatpak), "flatpak uninstall", backend::flatpak_remove_command(&flatpak), &flatpak),
+        (Some(Source::Cargo), "cargo uninstall", backend::cargo_remove_command(&cargo), &cargo),
+    ];
+    let mut remaining = Vec::new();
+    for (source, tool, command, names) in batches {
+        if let Err(e) = run_batch(ctx, tool, command, names) {
+            ctx.log.error(&e);
+            let batch: Vec<String> = present
+                .iter()
+                .filter(|entry| list::split_prefix(entry).0 == source)
+                .map(|entry| entry.to_string())
+                .collect();
+            failed.extend(batch.iter().cloned());
+            remaining.extend(batch);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(partial(failed, remaining));
+    }
+    ctx.log.success(format!("removed {} package(s)", present.len()));
+    Ok(Outcome::Applied(Box::new(record.clone())))
+}
+
+/// Status line of a packages record: how many of the added packages are still installed.
+pub(crate) fn status(record: &Record) -> String {
+    match backend::installed(&record_sources(record)) {
+        Ok(installed) => {
+            let missing: Vec<&str> = record
+                .packages
+                .iter()
+                .filter(|name| !installed.contains(*name))
+                .map(String::as_str)
+                .collect();
+            let mut status = format!(
+                "installed, {} of {} packages present",
+                record.packages.len() - missing.len(),
+                record.packages.len()
+            );
+            if !missing.is_empty() {
+                status.push_str(&format!(" (missing: {})", missing.join(", ")));
+            }
+            status
+        }
+        Err(_) => "installed".to_string(),
+    }
+}
+
+/// Reads a TOML package manifest (`*.toml`) or the legacy `packages.list` format.
+fn parse_groups(source_path: &str, content: &str) -> Result<Vec<PackageGroup>, InstallError> {
+    if Path::new(source_path).extension().is_some_and(|extension| extension == "toml") {
+        manifest::parse(content).map_err(|e| {
+            InstallError::Parse(format!("Invalid package manifest {}: {}", source_path, e.message()))
+        })
+    } else {
+        Ok(list::parse(content))
+    }
+}
+
+/// Drops packages whose `when` condition does not hold on this machine.
+fn applicable(ctx: &Context, mut groups: Vec<PackageGroup>) -> Vec<PackageGroup> {
+    if !groups.iter().flat_map(|group| &group.packages).any(|package| package.when.is_some()) {
+        return groups;
+    }
+
+    let system = System::detect();
+    for group in &mut groups {
+        group.packages.retain(|package| match &package.when {
+            Some(condition) if !system.satisfies(condition) => {
+                ctx.log.skip(format!("{}: requires {}", package.name, condition));
+                false
+            }
+            _ => true,
+        });
+    }
+    groups
+}
+
+/// Tool running one install transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Manager(PackageManager),
+    Flatpak,
+    Cargo,
+}
+
+/// Installs the packages of all groups with one transaction per tool:
+/// official-repository packages with pacman, the rest with the AUR helper (or
+/// everything with pacman when no helper is available), flatpak applications
+/// and cargo crates with their own tool. Returns the qualified names of the
+/// packages whose transaction failed or did not run.
+fn install_transactions(ctx: &Context, manager: PackageManager, groups: &[PackageGroup]) -> Vec<String> {
+    let mut transactions: Vec<(Tool, Vec<&Package>)> = Vec::new();
+    let mut not_installed = Vec::new();
+    for package in groups.iter().flat_map(|group| &group.packages) {
+        let tool = match package.source {
+            Some(Source::Flatpak) => Tool::Flatpak,
+            Some(Source::Cargo) => Tool::Cargo,
+            Some(Source::Repo) => Tool::Manager(PackageManager::Pacman),
+            Some(Source::Aur) | None if manager.supports_aur() => Tool::Manager(manager),
+            None => Tool::Manager(PackageManager::Pacman),
+            Some(Source::Aur) => {
+                ctx.log.error(format!("{} is an AUR package; install yay, paru or pikaur to install it.", package.name));
+                not_installed.push(package.qualified_name());
+                continue;
+            }
+        };
+        match transactions.iter_mut().find(|(existing, _)| *existing == tool) {
+            Some((_, packages)) => packages.push(package),
+            None => transactions.push((tool, vec![package])),
+        }
+    }
+    // Repository packages first, so that AUR builds find their dependencies.
+    transactions.sort_by_key(|(tool, _)| match tool {
+        Tool::Manager(PackageManager::Pacman) => 0,
+        Tool::Manager(_) => 1,
+        Tool::Flatpak => 2,
+        Tool::Cargo => 3,
+    });
+
+    let mut stop = false;
+    for (tool, packages) in &transactions {
+        let names: Vec<&str> = packages.iter().map(|package| package.name.as_str()).collect();
+        if stop {
+            not_installed.extend(packages.iter().map(|package| package.qualified_name()));
+            continue;
+        }
+        let (description, command) = match tool {
+            Tool::Manager(manager) => (format!("{} -S", manager.name()), manager.install_command(ctx.elevator, &names)),
+            Tool::Flatpak => ("flatpak install".to_string(), backend::flatpak_install_command(&names)),
+            Tool::Cargo => ("cargo install".to_string(), backend::cargo_install_command(&names)),
+        };
+        ctx.log.action("Installing", format!("{} package(s) with {}: {}", names.len(), description, names.join(" ")));
+        match progress::run(ctx, &description, command) {
+            Ok(()) => ctx.log.success(format!("installed {} package(s) with {}", names.len(), description)),
+            Err(e) => {
+                ctx.log.error(&e);
+                not_installed.extend(packages.iter().map(|package| package.qualified_name()));
+                stop = ctx.fail_fast;
+            }
+        }
+    }
+    not_installed
+}
+
+/// Runs a package tool on `packages`, doing nothing when the batch is empty.
+fn run_batch(ctx: &Context, tool: &str, mut command: Command, packages: &[&str]) -> Result<(), InstallError> {
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    ctx.log.action("Running", format!("{}: {:?}", tool, packages));
+    match command.status() {
+        Ok(status) if status.success() => {
+            ctx.log.success(format!("processed packages: {:?}", packages));
+            Ok(())
+        }
+        Ok(status) => Err(InstallError::failed(format!("{} {}", tool, packages.join(" ")), status, "")),
+        Err(e) => Err(InstallError::not_started(tool, e)),
+    }
+}
+
+/// Distinct package sources, used to only query the tools that are needed.
+fn sources(sources: impl Iterator<Item = Source>) -> Vec<Source> {
+    let mut distinct = Vec::new();
+    for source in sources {
+        if !distinct.contains(&source) {
+            distinct.push(source);
+        }
+    }
+    distinct
+}
+
+fn record_sources(record: &Record) -> Vec<Source> {
+    sources(record.packages.iter().filter_map(|entry| list::split_prefix(entry).0))
+}
+
+fn print_group_summary(ctx: &Context, group: &PackageGroup, resolution: &Resolution, succeeded: bool) {
+    for (old, new) in &resolution.renamed {
+        ctx.log.detail(format!("{} was renamed to {}", old, new));
+    }
+    for (name, reason) in &resolution.skipped {
+        ctx.log.skip(format!("{}: {}", name, reason));
+    }
+    if succeeded {
+        ctx.log.detail(format!(
+            "{}: {} installed, {} skipped",
+            group.name,
+            group.packages.len(),
+            resolution.skipped.len()
+        ));
+    }
+}
+
+/// Runs the `post_install` hooks of freshly installed packages.
+fn run_post_install(ctx: &Context, packages: &[Package]) -> Result<(), InstallError> {
+    for package in packages {
+        for hook in &package.post_install {
+            ctx.log.action("Running", format!("post-install hook of {}: {}", package.name, hook));
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(hook)
+                .output()
+                .map_err(|e| InstallError::not_started(hook, e))?;
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                ctx.log.output(line);
+            }
+            if !output.status.success() {
+                return Err(InstallError::failed(hook, output.status, &String::from_utf8_lossy(&output.stderr)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Picks the packages to install per group: from `--groups`/`--packages`,
+/// interactively, or every default package when stdin is not a terminal.
+/// Groups left without packages are dropped.
+fn select_packages(ctx: &Context, groups: Vec<PackageGroup>) -> Result<Vec<PackageGroup>, InstallError> {
+    let selected = if !ctx.packages.is_empty() {
+        select_from_options(ctx, groups)?
+    } else if io::stdin().is_terminal() && !ctx.assume_yes {
+        prompt_packages(ctx, groups)
+    } else {
+        groups
+            .into_iter()
+            .map(|mut group| {
+                group.packages.retain(|package| !package.suggested);
+                group
+            })
+            .collect()
+    };
+
+    Ok(selected.into_iter().filter(|group| !group.packages.is_empty()).collect())
+}
+
+fn select_from_options(ctx: &Context, mut groups: Vec<PackageGroup>) -> Result<Vec<PackageGroup>, InstallError> {
+    for name in &ctx.packages.groups {
+        if !groups.iter().any(|group| group.name.eq_ignore_ascii_case(name)) {
+            let available: Vec<&str> = groups.iter().map(|group| group.name.as_str()).collect();
+            return Err(InstallError::Selection(format!(
+                "No package group matches '{}'. Available groups: {}",
+                name,
+                available.join(", ")
+            )));
+        }
+    }
+    for name in &ctx.packages.names {
+        if !groups.iter().flat_map(|group| &group.packages).any(|package| package_matches(package, name)) {
+            return Err(InstallError::Selection(format!(
+                "Package '{}' is not listed or its condition does not hold on this system.",
+                name
+            )));
+        }
+    }
+
+    for group in &mut groups {
+        let whole_group = ctx.packages.groups.iter().any(|name| group.name.eq_ignore_ascii_case(name));
+        group
+            .packages
+            .retain(|package| {
+                (whole_group && !package.suggested) || ctx.packages.names.iter().any(|name| package_matches(package, name))
+            });
+    }
+    Ok(groups)
+}
+
+fn prompt_packages(ctx: &Context, groups: Vec<PackageGroup>) -> Vec<PackageGroup> {
+    let names: Vec<&str> = groups.iter().map(|group| group.name.as_str()).collect();
+    let chosen = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{}\n",
+            "Select package groups:\n [a] All   [space] Toggle   [enter] Confirm".yellow()
+        ))
+        .items(&names)
+        .defaults(&vec![true; names.len()])
+        .interact_opt()
+        .unwrap_or_else(|e| {
+            ctx.log.error(format!("Failed to select options: {}", e));
+            None
+        })
+        .unwrap_or_default();
+
+    let mut selected = Vec::new();
+    for index in chosen {
+        let group = &groups[index];
+        let items: Vec<String> = group.packages.iter().map(package_label).collect();
+        let defaults: Vec<bool> = group.packages.iter().map(|package| !package.suggested).collect();
+        let indices = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("{}\n", format!("Select packages from {}:", group.name).yellow()))
+            .items(&items)
+            .defaults(&defaults)
+            .interact_opt()
+            .unwrap_or_else(|e| {
+                ctx.log.error(format!("Failed to select options: {}", e));
+                None
+            })
+            .unwrap_or_default();
+        let packages = indices.into_iter().map(|i| group.packages[i].clone()).collect();
+        selected.push(PackageGroup { name: group.name.clone(), packages });
+    }
+    selected
+}
+
+/// `--packages` names match with or without the `flatpak:`/`cargo:` prefix.
+fn package_matches(package: &Package, name: &str) -> bool {
+    package.name == name || package.qualified_name() == name
+}
+
+fn package_label(package: &Package) -> String {
+    let mut label = package.name.clone();
+    if let Some(source) = package.source {
+        label.push_str(&format!(" [{}]", source.name()));
+    }
+    if let Some(description) = &package.description {
+        label.push_str(&format!(" - {}", description));
+    }
+    if package.suggested {
+        label.push_str(&format!(" {}", "(suggested)".dimmed()));
+    }
+    label
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+use regex::Regex;
+
+use crate::context::Context;
+use crate::install::InstallError;
+
+/// A step of a pacman or AUR helper transaction.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    Downloading(String),
+    /// `(index/total) <action> <package>`, e.g. installing or upgrading.
+    Transaction { index: usize, total: usize, action: String, package: String },
+    /// AUR helpers building a package (`==> Making package: <name>`).
+    Building(String),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Downloading(package) => write!(f, "downloading {}", package),
+            Step::Transaction { index, total, action, package } => {
+                write!(f, "[{}/{}] {} {}", index, total, action, package)
+            }
+            Step::Building(package) => write!(f, "building {}", package),
+        }
+    }
+}
+
+/// Recognizes the progress lines pacman and makepkg print when their output is not a terminal.
+pub(crate) fn parse_step(line: &str) -> Option<Step> {
+    static PATTERNS: OnceLock<[Regex; 3]> = OnceLock::new();
+    let [download, transaction, build] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"^\s*(\S+) downloading\.\.\.$").unwrap(),
+            Regex::new(r"^\((\d+)/(\d+)\) (installing|upgrading|reinstalling|downgrading) (\S+)").unwrap(),
+            Regex::new(r"^==> Making package: (\S+)").unwrap(),
+        ]
+    });
+
+    if let Some(captures) = transaction.captures(line) {
+        return Some(Step::Transaction {
+            index: captures[1].parse().ok()?,
+            total: captures[2].parse().ok()?,
+            action: captures[3].to_string(),
+            package: captures[4].to_string(),
+        });
+    }
+    if let Some(captures) = download.captures(line) {
+        return Some(Step::Downloading(captures[1].to_string()));
+    }
+    build.captures(line).map(|captures| Step::Building(captures[1].to_string()))
+}
+
+/// Runs a package tool with its output piped: recognized steps are shown as
+/// progress, every line goes to the run log and the last lines of stderr are
+/// included in the error when the command fails.
+pub(crate) fn run(ctx: &Context, description: &str, mut command: Command) -> Result<(), InstallError> {
+    let mut child = command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| InstallError::not_started(description, e))?;
+
+    let stderr = child.stderr.take().map(|stderr| {
+        let log = ctx.log.clone();
+        thread::spawn(move || {
+            let mut tail: Vec<String> = Vec::new();
+            for line in lines(stderr) {
+                log.capture(&line);
+                if tail.len() == 5 {
+                    tail.remove(0);
+                }
+                tail.push(line);
+            }
+            tail
+        })
+    });
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in lines(stdout) {
+            ctx.log.capture(&line);
+            if let Some(step) = parse_step(&line) {
+                ctx.log.progress(step);
+            }
+        }
+    }
+    ctx.log.finish_progress();
+
+    let status = child
+        .wait()
+        .map_err(|e| InstallError::command(description, format!("could not be waited for: {}", e)))?;
+    let tail = stderr.and_then(|handle| handle.join().ok()).unwrap_or_default();
+    if !status.success() {
+        return Err(InstallError::failed(description, status, &tail.join(" | ")));
+    }
+    Ok(())
+}
+
+/// Lines of a child output stream, also split at carriage returns of progress bars.
+fn lines(stream: impl Read) -> impl Iterator<Item = String> {
+    BufReader::new(stream)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .flat_map(|line| {
+            String::from_utf8_lossy(&line)
+                .split('\r')
+                .map(|part| part.trim_end().to_string())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+        })
+}
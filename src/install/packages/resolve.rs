@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::process::Command;
+
+use crate::install::InstallError;
+
+use super::backend::PackageManager;
+use super::list::{Package, PackageGroup, Source};
+
+/// Why a selected package is left out of the install transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SkipReason {
+    Installed,
+    NotFound,
+    /// Conflicts with an installed package or one selected before it.
+    Conflict(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Installed => write!(f, "already installed"),
+            SkipReason::NotFound => write!(f, "not found"),
+            SkipReason::Conflict(other) => write!(f, "conflicts with {}", other),
+        }
+    }
+}
+
+/// Outcome of resolving one group.
+#[derive(Debug, Default)]
+pub(crate) struct Resolution {
+    /// `(listed name, new name)` of packages that are now provided under another name.
+    pub renamed: Vec<(String, String)>,
+    pub skipped: Vec<(String, SkipReason)>,
+    /// Skipped packages that are installed already; their services and
+    /// groups are still set up.
+    pub present: Vec<Package>,
+}
+
+/// Repositories of Arch Linux itself, installed with pacman.
+const OFFICIAL_REPOSITORIES: [&str; 3] = ["core", "extra", "multilib"];
+
+/// `-Si` fields used for resolution and export.
+#[derive(Debug, Default)]
+pub(super) struct PackageInfo {
+    pub repository: Option<String>,
+    pub conflicts: Vec<String>,
+}
+
+impl PackageInfo {
+    /// Where a package without an explicit source is installed from: the
+    /// official repositories with pacman, anything else with the AUR helper.
+    fn source(&self) -> Source {
+        match &self.repository {
+            Some(repository) if OFFICIAL_REPOSITORIES.contains(&repository.as_str()) => Source::Repo,
+            _ => Source::Aur,
+        }
+    }
+}
+
+/// Checks every selected package against the package databases before
+/// installing: packages that are installed already, cannot be found or
+/// conflict with an installed package or one accepted before them are
+/// removed from their group, so of two conflicting packages the first listed
+/// one is installed. Renamed ones are replaced by their new name. Installed
+/// ones move to `present` of the resolution. `installed` holds qualified names.
+pub(crate) fn resolve(
+    manager: PackageManager,
+    installed: &HashSet<String>,
+    groups: &mut [PackageGroup],
+) -> Result<Vec<Resolution>, InstallError> {
+    let mut repo = Vec::new();
+    let mut other = Vec::new();
+    for package in groups.iter().flat_map(|group| &group.packages) {
+        if package.source.is_some_and(Source::is_external) {
+            continue;
+        } else if package.source == Some(Source::Repo) || !manager.supports_aur() {
+            repo.push(package.name.as_str());
+        } else {
+            other.push(package.name.as_str());
+        }
+    }
+    let mut found = query(PackageManager::Pacman, &repo)?;
+    found.extend(query(manager, &other)?);
+
+    // Packages of this pass that are going to be installed, in list order.
+    let mut accepted: HashSet<String> = HashSet::new();
+    let mut resolutions = Vec::new();
+    for group in groups.iter_mut() {
+        let mut resolution = Resolution::default();
+        let mut packages = Vec::new();
+        for mut package in group.packages.drain(..) {
+            // Flatpak and cargo packages are looked up by their own tool when installing.
+            if package.source.is_some_and(Source::is_external) {
+                if installed.contains(&package.qualified_name()) {
+                    resolution.skipped.push((package.qualified_name(), SkipReason::Installed));
+                    resolution.present.push(package);
+                } else {
+                    packages.push(package);
+                }
+                continue;
+            }
+            // With an AUR helper, packages without a source are split by
+            // the repository `-Si` found them in.
+            if package.source.is_none() && manager.supports_aur() {
+                package.source = found.get(&package.name).map(PackageInfo::source);
+            }
+            // An installed package counts as present even when `-Si` does not
+            // know it, e.g. one built from the AUR or installed from a file.
+            let reason = match found.get(&package.name) {
+                _ if installed.contains(&package.name) => Some(SkipReason::Installed),
+                Some(info) => info
+                    .conflicts
+                    .iter()
+                    .find(|name| **name != package.name && (installed.contains(*name) || accepted.contains(*name)))
+                    .or_else(|| {
+                        // `Conflicts With` may only be declared by the other package.
+                        accepted.iter().find(|name| {
+                            found.get(*name).is_some_and(|other| other.conflicts.contains(&package.name))
+                        })
+                    })
+                    .map(|name| SkipReason::Conflict(name.clone())),
+                None => match provider(&package.name)? {
+                    Some(new_name) if installed.contains(&new_name) => {
+                        package.name = new_name;
+                        Some(SkipReason::Installed)
+                    }
+                    Some(new_name) => {
+                        resolution.renamed.push((package.name.clone(), new_name.clone()));
+                        package.name = new_name;
+                        None
+                    }
+                    None => Some(SkipReason::NotFound),
+                },
+            };
+            match reason {
+                Some(SkipReason::Installed) => {
+                    resolution.skipped.push((package.name.clone(), SkipReason::Installed));
+                    resolution.present.push(package);
+                }
+                Some(reason) => resolution.skipped.push((package.name, reason)),
+                None => {
+                    accepted.insert(package.name.clone());
+                    packages.push(package);
+                }
+            }
+        }
+        group.packages = packages;
+        resolutions.push(resolution);
+    }
+    Ok(resolutions)
+}
+
+/// Runs `<manager> -Si` for `names`. Unknown names only produce an error on
+/// stderr, so the exit status is ignored and the found packages are parsed.
+pub(super) fn query(manager: PackageManager, names: &[&str]) -> Result<HashMap<String, PackageInfo>, InstallError> {
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let output = manager
+        .info_command(names)
+        .output()
+        .map_err(|e| InstallError::not_started(format!("{} -Si", manager.name()), e))?;
+    Ok(parse_info(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the `Field : value` blocks printed by `-Si`, separated by empty lines.
+fn parse_info(output: &str) -> HashMap<String, PackageInfo> {
+    let mut packages = HashMap::new();
+    for block in output.split("\n\n") {
+        let mut name = None;
+        let mut info = PackageInfo::default();
+        for line in block.lines() {
+            let Some((field, value)) = line.split_once(" : ").or_else(|| line.split_once(": ")) else {
+                continue;
+            };
+            let value = value.trim();
+            match field.trim() {
+                "Name" => name = Some(value.to_string()),
+                "Repository" => info.repository = Some(value.to_string()),
+                "Conflicts With" if value != "None" => {
+                    info.conflicts = value
+                        .split_whitespace()
+                        .map(|conflict| conflict.split(['<', '>', '=']).next().unwrap_or(conflict).to_string())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        if let Some(name) = name {
+            packages.insert(name, info);
+        }
+    }
+    packages
+}
+
+/// New name of a package that is not in the repositories under `name` but
+/// still provided by another package (`pacman -Sp` follows provides).
+fn provider(name: &str) -> Result<Option<String>, InstallError> {
+    let output = Command::new("pacman")
+        .args(["-Sp", "--print-format", "%n", name])
+        .output()
+        .map_err(|e| InstallError::not_started("pacman -Sp", e))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && *line != name)
+        .map(str::to_string))
+}
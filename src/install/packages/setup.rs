@@ -0,0 +1,131 @@
+use std::process::Command;
+
+use crate::context::Context;
+use crate::install::{command_output, run_command, InstallError};
+use crate::state::Record;
+
+use super::list::Package;
+
+/// Prefix of user services (`systemctl --user`) in install records.
+const USER_SERVICE: &str = "user:";
+
+/// Services enabled and groups joined while setting up installed packages.
+#[derive(Debug, Default)]
+pub(crate) struct Changes {
+    /// System services, and user services prefixed with `user:`.
+    pub services: Vec<String>,
+    pub user_groups: Vec<String>,
+}
+
+/// Enables and starts the declared services and adds the user to the declared
+/// groups. Only services that were not enabled and groups the user was not a
+/// member of are recorded, so uninstall reverts exactly what was changed.
+pub(crate) fn apply<'a>(
+    ctx: &Context,
+    packages: impl IntoIterator<Item = &'a Package>,
+    changes: &mut Changes,
+) -> Result<(), InstallError> {
+    for package in packages {
+        for service in &package.services {
+            if !is_enabled(service, false) {
+                ctx.log.action("Enabling", format!("{} for {}", service, package.name));
+                run_command(ctx.elevator.command("systemctl").args(["enable", "--now", service]))?;
+                changes.services.push(service.clone());
+            }
+        }
+        for service in &package.user_services {
+            if !is_enabled(service, true) {
+                ctx.log.action("Enabling", format!("user service {} for {}", service, package.name));
+                run_command(Command::new("systemctl").args(["--user", "enable", "--now", service]))?;
+                changes.services.push(format!("{}{}", USER_SERVICE, service));
+            }
+        }
+        if package.user_groups.is_empty() {
+            continue;
+        }
+        let user = current_user()?;
+        let member_of = user_groups(&user)?;
+        for group in package.user_groups.iter().filter(|group| !member_of.contains(group)) {
+            ctx.log.action("Adding", format!("{} to the {} group for {}", user, group, package.name));
+            run_command(ctx.elevator.command("usermod").args(["-aG", group, &user]))?;
+            changes.user_groups.push(group.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Lists the changes that only take effect after logging in again.
+pub(crate) fn print_relogin_summary(ctx: &Context, changes: &Changes) {
+    if !changes.user_groups.is_empty() {
+        ctx.log.warn(format!(
+            "Log out and back in for the new group membership ({}) to take effect.",
+            changes.user_groups.join(", ")
+        ));
+    }
+}
+
+/// Disables the recorded services and leaves the recorded groups. A failure
+/// is logged and the rest is still reverted; the returned changes are the
+/// ones that are still in place.
+pub(crate) fn revert(ctx: &Context, record: &Record) -> Changes {
+    let mut remaining = Changes::default();
+    for service in &record.services {
+        let result = match service.strip_prefix(USER_SERVICE) {
+            Some(name) => {
+                ctx.log.action("Disabling", format!("user service {}", name));
+                run_command(Command::new("systemctl").args(["--user", "disable", "--now", name]))
+            }
+            None => {
+                ctx.log.action("Disabling", service);
+                run_command(ctx.elevator.command("systemctl").args(["disable", "--now", service]))
+            }
+        };
+        if let Err(e) = result {
+            ctx.log.error(&e);
+            remaining.services.push(service.clone());
+        }
+    }
+    if record.user_groups.is_empty() {
+        return remaining;
+    }
+    let user = match current_user() {
+        Ok(user) => user,
+        Err(e) => {
+            ctx.log.error(&e);
+            remaining.user_groups = record.user_groups.clone();
+            return remaining;
+        }
+    };
+    for group in &record.user_groups {
+        ctx.log.action("Removing", format!("{} from the {} group", user, group));
+        if let Err(e) = run_command(ctx.elevator.command("gpasswd").args(["-d", &user, group])) {
+            ctx.log.error(&e);
+            remaining.user_groups.push(group.clone());
+        }
+    }
+    remaining
+}
+
+fn is_enabled(service: &str, user: bool) -> bool {
+    let mut command = Command::new("systemctl");
+    if user {
+        command.arg("--user");
+    }
+    command
+        .args(["is-enabled", "--quiet", service])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// `$USER`, or `id -un` when it is not set.
+fn current_user() -> Result<String, InstallError> {
+    if let Some(user) = std::env::var("USER").ok().filter(|user| !user.is_empty()) {
+        return Ok(user);
+    }
+    let output = command_output(Command::new("id").arg("-un"))?;
+    Ok(output.trim().to_string())
+}
+
+fn user_groups(user: &str) -> Result<Vec<String>, InstallError> {
+    Ok(command_output(Command::new("id").args(["-nG", user]))?.split_whitespace().map(str::to_string).collect())
+}
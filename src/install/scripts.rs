@@ -1,4 +1,3 @@
-use colored::*;
 use std::fs;
 use std::process::{Command, Stdio};
 use std::io::{BufRead, BufReader};
@@ -6,46 +5,35 @@ use std::thread;
 use std::time::Duration;
 use std::os::unix::process::ExitStatusExt;
 
-use crate::install::FileEntry;
+use crate::context::Context;
+use crate::install::{FileEntry, InstallError, Outcome};
+use crate::state::Record;
 
-pub fn install(choice: &FileEntry) {
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "true";
-
-    println!(
-        "{} {}",
-        ":: Installing".blue(),
-        choice.title
-    );
+pub(crate) fn install(ctx: &Context, choice: &FileEntry) -> Result<Outcome, InstallError> {
+    ctx.log.action("Installing", &choice.title);
 
     // Ensure the target directory exists in the user's home directory
     let home_dir = std::env::var("HOME").unwrap_or_default();
     let target_dir = format!("{}/scripts", home_dir);
     if let Err(e) = fs::create_dir_all(&target_dir) {
-        eprintln!("{} Failed to create target directory {}: {}", ":: Error:".red(), target_dir, e);
-        return;
+        return Err(InstallError::io(format!("Failed to create target directory {}", target_dir), e));
     }
 
     // Copy the script file to the target directory
     if let Some(source_path) = &choice.source_path {
-        let target_path = format!("{}/{}", target_dir, source_path.split('/').last().unwrap_or_default());
-        if debug_mode {
-            println!("{} Copying from {} to {}", ":: Debug:".blue(), source_path, target_path);
-        }
+        let target_path = format!("{}/{}", target_dir, source_path.split('/').next_back().unwrap_or_default());
+        ctx.log.debug(format!("Copying from {} to {}", source_path, target_path));
         if let Err(e) = fs::copy(source_path, &target_path) {
-            eprintln!("{} Failed to copy file from {} to {}: {}", ":: Error:".red(), source_path, target_path, e);
-            return;
+            return Err(InstallError::io(format!("Failed to copy file from {} to {}", source_path, target_path), e));
         }
     } else {
-        eprintln!("{} Source path is missing for the script installation.", ":: Error:".red());
-        return;
+        return Err(InstallError::MissingPath("Source path is missing for the script installation.".to_string()));
     }
 
-    if debug_mode {
-        println!("{} Attempting to execute the script after copying.", ":: Debug:".blue());
-    }
+    ctx.log.debug("Attempting to execute the script after copying.");
 
     // Construct the command to execute the script
-    let target_path = format!("{}/{}", target_dir, choice.source_path.as_ref().unwrap().split('/').last().unwrap_or_default());
+    let target_path = format!("{}/{}", target_dir, choice.source_path.as_ref().unwrap().split('/').next_back().unwrap_or_default());
 
     
     // NOTE =============== Warning ===============
@@ -57,17 +45,21 @@ pub fn install(choice: &FileEntry) {
         .arg(&target_path)
         .stdout(Stdio::piped())  // Capture standard output
         .spawn()
-        .expect("Failed to start script process");
+        .map_err(|e| InstallError::command(format!("bash {}", target_path), format!("failed to start: {}", e)))?;
 
-    let stdout = child.stdout.take().expect("Failed to take stdout of child process");
+    let stdout = match child.stdout.take() {
+        Some(stdout) => stdout,
+        None => return Err(InstallError::command(format!("bash {}", target_path), "has no captured stdout")),
+    };
     let reader = BufReader::new(stdout);
 
     // Use a separate thread to handle the output
+    let log = ctx.log.clone();
     thread::spawn(move || {
         for line in reader.lines() {
             match line {
-                Ok(line) => println!("    {} {}", "[OUTPUT]".yellow(), line.trim_end()),
-                Err(e) => eprintln!("    {} Error reading script output: {}", "[ERROR]".red(), e),
+                Ok(line) => log.output(line.trim_end()),
+                Err(e) => log.error(format!("Error reading script output: {}", e)),
             }
         }
     });
@@ -75,36 +67,32 @@ pub fn install(choice: &FileEntry) {
     // Allow the script to run for a limited time before killing it
     thread::sleep(Duration::from_secs(3));
     match child.kill() {
-        Ok(_) => {
-            if debug_mode {
-                println!("{} Script terminated after 3 seconds.", "::".green());
-            }
-        },
-        Err(e) => eprintln!("{} Failed to terminate script: {}", ":: Error:".red(), e),
+        Ok(_) => ctx.log.debug("Script terminated after 3 seconds."),
+        Err(e) => ctx.log.error(format!("Failed to terminate script: {}", e)),
     }
     // Attempt to collect the exit status after killing the process
     match child.wait() {
         Ok(status) if status.success() => {
-            println!("{} Script was running successfully before termination.", "  ->".green());
+            ctx.log.detail("Script was running successfully before termination.");
         },
         Ok(status) => {
             match status.code() {
                 Some(9) | None if status.signal() == Some(9) => {
-                    println!("{} Script was terminated after 3 seconds with SIGKILL (expected).", "  ->".green());
+                    ctx.log.detail("Script was terminated after 3 seconds with SIGKILL (expected).");
                 },
                 _ => {
-                    eprintln!("{} Script was terminated with unexpected exit code: {}", ":: Error:".red(), status);
+                    ctx.log.error(format!("Script was terminated with unexpected exit code: {}", status));
                 }
             }
         },
         Err(e) => {
-            eprintln!("{} Failed to retrieve script exit status: {}", ":: Error:".red(), e);
+            ctx.log.error(format!("Failed to retrieve script exit status: {}", e));
         }
     }
 
-    println!(
-        "{} installed script in {}",
-        "  -> Successfully".green(),
-        target_path
-    );
+    ctx.log.success(format!("installed script in {}", target_path));
+    Ok(Outcome::Applied(Box::new(Record {
+        files: vec![target_path],
+        ..Record::default()
+    })))
 }
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use walkdir::WalkDir;
+
+use crate::context::Context;
+use crate::install::InstallError;
+use crate::state::Record;
+
+/// 64-bit FNV-1a, enough to tell changed files apart and stable across
+/// Rust versions, unlike `DefaultHasher`.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Checksum of `content` as stored in the install state.
+pub(crate) fn checksum(content: &[u8]) -> String {
+    let mut hasher = Fnv1a::default();
+    hasher.write(content);
+    format!("{:016x}", hasher.finish())
+}
+
+/// What [`sync_dir`] did.
+#[derive(Default)]
+pub(crate) struct Synced {
+    /// Files hyde-ext owns in the target after the sync.
+    pub files: Vec<String>,
+    /// Checksums of the files hyde-ext owns, as they were written.
+    pub checksums: BTreeMap<String, String>,
+    /// Number of files written.
+    pub copied: usize,
+    /// Files left alone because they were changed and overwriting was declined.
+    pub kept: Vec<String>,
+    /// Files that could not be copied, with the error.
+    pub failed: Vec<String>,
+}
+
+/// Copies the files of `source` and its subdirectories to `target`,
+/// creating the directories. Files with the content of the source are not
+/// written again. A file that differs is only replaced without asking when
+/// hyde-ext wrote it and it is unchanged since, according to `previous`;
+/// otherwise it may hold the user's changes and is replaced with `--force`,
+/// `--yes` or a confirmation.
+pub(crate) fn sync_dir(
+    ctx: &Context,
+    source: &Path,
+    target: &Path,
+    previous: Option<&Record>,
+) -> Result<Synced, InstallError> {
+    if !source.is_dir() {
+        return Err(InstallError::MissingPath(format!("Source directory {} does not exist.", source.display())));
+    }
+    let mut synced = Synced::default();
+    for entry in WalkDir::new(source).min_depth(1).sort_by_file_name() {
+        let entry = entry.map_err(|e| {
+            InstallError::io(format!("Error reading source directory {}", source.display()), io::Error::from(e))
+        })?;
+        let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+        let destination = target.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)
+                .map_err(|e| InstallError::io(format!("Failed to create directory {}", destination.display()), e))?;
+            continue;
+        }
+        if let Err(e) = sync_file(ctx, entry.path(), &destination, previous, &mut synced) {
+            ctx.log.error(format!("Error copying file {}: {}", entry.path().display(), e));
+            synced.failed.push(entry.path().display().to_string());
+        }
+    }
+
+    // Recorded files that were not synced, because their copy failed or their
+    // source is gone, stay recorded so that uninstall still removes them.
+    synced.files = synced.checksums.keys().cloned().collect();
+    for file in previous.map_or(&[][..], |record| &record.files) {
+        if synced.files.contains(file) || synced.kept.contains(file) || !Path::new(file).exists() {
+            continue;
+        }
+        if let Some(sum) = previous.and_then(|record| record.checksums.get(file)) {
+            synced.checksums.insert(file.clone(), sum.clone());
+        }
+        synced.files.push(file.clone());
+    }
+    Ok(synced)
+}
+
+fn sync_file(
+    ctx: &Context,
+    source: &Path,
+    destination: &Path,
+    previous: Option<&Record>,
+    synced: &mut Synced,
+) -> io::Result<()> {
+    let content = fs::read(source)?;
+    let sum = checksum(&content);
+    let name = destination.to_string_lossy().into_owned();
+    let recorded = previous.and_then(|record| record.checksums.get(&name));
+
+    match fs::read(destination) {
+        Ok(existing) if existing == content => {
+            ctx.log.trace(format!("Up to date: {}", name));
+            // Only a file hyde-ext wrote is removed on uninstall, not an
+            // identical one the user already had.
+            if recorded.is_some() || previous.is_some_and(|record| record.files.contains(&name)) {
+                synced.checksums.insert(name, sum);
+            }
+            return Ok(());
+        }
+        Ok(existing) if recorded != Some(&checksum(&existing)) && !overwrite(ctx, destination)? => {
+            ctx.log.skip(format!("{} has changes of its own, use --force to replace it", name));
+            synced.kept.push(name);
+            return Ok(());
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(destination, &content)?;
+    ctx.log.debug(format!("Copied {} to {}", source.display(), name));
+    synced.copied += 1;
+    synced.checksums.insert(name, sum);
+    Ok(())
+}
+
+/// Whether a file that differs from the source may be replaced. Without a
+/// terminal to ask on, the prompt's default answer (no) is kept.
+fn overwrite(ctx: &Context, destination: &Path) -> io::Result<bool> {
+    if ctx.force || ctx.assume_yes {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} '{}' differs from the shipped file and may contain your changes. Replace it?",
+            ":: Warning:".yellow(),
+            destination.display()
+        ))
+        .default(false)
+        .interact()
+        .map_err(|e| io::Error::other(e.to_string()))
+}
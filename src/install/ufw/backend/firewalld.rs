@@ -0,0 +1,151 @@
+use std::process::Command;
+
+use crate::install::InstallError;
+use crate::privilege::Elevator;
+
+use super::super::rules::{Action, Direction, Policies, Policy, Rule};
+use super::{args, query, Backend, Installed, Snapshot};
+
+/// Logged denied packets, the counterpart of ufw's medium logging.
+const LOG_DENIED: &str = "unicast";
+
+/// firewalld, configured in its default zone. Ports and services without
+/// addresses are added as such, everything else as a rich rule. While the
+/// daemon is stopped the permanent configuration is changed with
+/// `firewall-offline-cmd`.
+pub(crate) struct Firewalld {
+    pub running: bool,
+    pub elevator: Elevator,
+}
+
+/// Whether the firewalld daemon is running (`firewall-cmd --state`).
+pub(super) fn running() -> bool {
+    Command::new("firewall-cmd").arg("--state").output().is_ok_and(|output| output.status.success())
+}
+
+impl Firewalld {
+    /// `firewall-cmd --permanent` while running, `firewall-offline-cmd` otherwise.
+    fn command(&self, option: &str) -> Vec<String> {
+        if self.running {
+            args(&["firewall-cmd", "--permanent", option])
+        } else {
+            args(&["firewall-offline-cmd", option])
+        }
+    }
+
+    fn list(&self, option: &str) -> Result<String, InstallError> {
+        let command = self.command(option);
+        query(self.elevator, &command.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+}
+
+impl Backend for Firewalld {
+    fn name(&self) -> &'static str {
+        "firewalld"
+    }
+
+    fn snapshot(&self, policies: Policies) -> Result<Snapshot, InstallError> {
+        // The zone rejects what it does not allow and never filters outgoing traffic.
+        if policies.incoming == Policy::Allow || policies.outgoing != Policy::Allow {
+            return Err(InstallError::Conflict(
+                "firewalld zones deny incoming and allow outgoing traffic, other policies are not supported"
+                    .to_string(),
+            ));
+        }
+        let mut rules = Vec::new();
+        for (kind, option) in [("port", "--list-ports"), ("service", "--list-services")] {
+            for value in self.list(option)?.split_whitespace() {
+                rules.push(installed(kind, value));
+            }
+        }
+        for rule in self.list("--list-rich-rules")?.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            rules.push(installed("rich-rule", rule));
+        }
+
+        let program = if self.running { "firewall-cmd" } else { "firewall-offline-cmd" };
+        let mut pending = Vec::new();
+        if query(self.elevator, &[program, "--get-log-denied"])?.trim() != LOG_DENIED {
+            pending.push(vec![program.to_string(), format!("--set-log-denied={}", LOG_DENIED)]);
+        }
+        Ok(Snapshot { active: self.running, pending, rules, builtin: Vec::new() })
+    }
+
+    fn key(&self, rule: &Rule) -> Result<String, String> {
+        if rule.direction == Direction::Out {
+            return Err("firewalld zones only filter incoming traffic".to_string());
+        }
+        if rule.interface.is_some() {
+            return Err("firewalld binds interfaces to zones, not to single rules".to_string());
+        }
+        let port = rule.port.as_ref().map(|port| port.replace(':', "-"));
+        let proto = rule.proto.map(|proto| proto.name());
+        let service = match (&port, proto, &rule.app) {
+            (Some(port), None, _) if !port.starts_with(|c: char| c.is_ascii_digit()) => Some(port.clone()),
+            (Some(port), None, _) => return Err(format!("firewalld needs a proto for port {}", port)),
+            (None, _, Some(app)) => Some(app.clone()),
+            _ => None,
+        };
+
+        if rule.action == Action::Allow && rule.from.is_none() && rule.to.is_none() {
+            return Ok(match (service, port, proto) {
+                (Some(service), _, _) => format!("service {}", service),
+                (None, Some(port), Some(proto)) => format!("port {}/{}", port, proto),
+                _ => return Err("the rule has no port".to_string()),
+            });
+        }
+
+        let address = rule.from.iter().chain(&rule.to).next();
+        let mut rich = vec!["rule".to_string()];
+        if let Some(address) = address {
+            let family = if address.contains(':') { "ipv6" } else { "ipv4" };
+            rich.push(format!("family=\"{}\"", family));
+        }
+        if let Some(from) = &rule.from {
+            rich.push(format!("source address=\"{}\"", from));
+        }
+        if let Some(to) = &rule.to {
+            rich.push(format!("destination address=\"{}\"", to));
+        }
+        match (service, port, proto) {
+            (Some(service), _, _) => rich.push(format!("service name=\"{}\"", service)),
+            (None, Some(port), Some(proto)) => rich.push(format!("port port=\"{}\" protocol=\"{}\"", port, proto)),
+            _ => {}
+        }
+        rich.push(
+            match rule.action {
+                Action::Allow => "accept",
+                Action::Deny => "drop",
+                Action::Reject => "reject",
+                Action::Limit => "accept limit value=\"6/m\"",
+            }
+            .to_string(),
+        );
+        Ok(format!("rich-rule {}", rich.join(" ")))
+    }
+
+    fn add(&self, rule: &Rule) -> Result<Vec<String>, String> {
+        let key = self.key(rule)?;
+        let (kind, value) = key.split_once(' ').unwrap_or_default();
+        Ok(self.command(&format!("--add-{}={}", kind, value)))
+    }
+
+    fn remove(&self, rule: &Installed) -> Vec<String> {
+        self.command(&format!("--remove-{}={}", rule.handle[0], rule.handle[1]))
+    }
+
+    fn commit(&self) -> Option<Vec<String>> {
+        self.running.then(|| args(&["firewall-cmd", "--reload"]))
+    }
+
+    fn enable(&self) -> Option<Vec<String>> {
+        Some(args(&["systemctl", "enable", "--now", "firewalld"]))
+    }
+}
+
+fn installed(kind: &str, value: &str) -> Installed {
+    Installed {
+        key: Some(format!("{} {}", kind, value)),
+        display: format!("{} {}", kind, value),
+        handle: vec![kind.to_string(), value.to_string()],
+    }
+}
@@ -0,0 +1,104 @@
+mod firewalld;
+mod nftables;
+mod ufw;
+
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::install::{command_output, InstallError};
+use crate::privilege::{on_path, Elevator};
+
+use super::rules::{Policies, Rule};
+
+/// Firewall implementation selected with `backend` in the `[firewall]` table
+/// of `UFW.toml`, or detected when it is omitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    Ufw,
+    Firewalld,
+    Nftables,
+}
+
+/// A rule reported by the firewall.
+#[derive(Debug, Clone)]
+pub(crate) struct Installed {
+    /// Comparable with [`Backend::key`]; `None` when the rule could not be parsed.
+    pub key: Option<String>,
+    /// The rule as shown to the user.
+    pub display: String,
+    /// What the backend needs to delete the rule.
+    pub handle: Vec<String>,
+}
+
+/// Current state of the firewall.
+#[derive(Debug, Default)]
+pub(crate) struct Snapshot {
+    pub active: bool,
+    /// Commands setting up default policies and logging that are not in place yet.
+    pub pending: Vec<Vec<String>>,
+    pub rules: Vec<Installed>,
+    /// Rules the backend sets up itself, left out of the unknown rules.
+    pub builtin: Vec<String>,
+}
+
+/// Translates the declarative rules of `UFW.toml` into the commands of one
+/// firewall. Commands are returned as argument lists and run as root by the
+/// handler, so every backend can be tested with stubbed commands.
+pub(crate) trait Backend {
+    fn name(&self) -> &'static str;
+
+    /// Reads the policies and rules currently configured; the commands
+    /// setting the default `policies` are pending when they differ.
+    fn snapshot(&self, policies: Policies) -> Result<Snapshot, InstallError>;
+
+    /// Identity of a catalog rule, comparable with [`Installed::key`], or why
+    /// the backend cannot express the rule.
+    fn key(&self, rule: &Rule) -> Result<String, String>;
+
+    /// Brings a key recorded by an earlier run into the current form.
+    fn normalize(&self, key: &str) -> Option<String> {
+        Some(key.to_string())
+    }
+
+    fn add(&self, rule: &Rule) -> Result<Vec<String>, String>;
+
+    fn remove(&self, rule: &Installed) -> Vec<String>;
+
+    /// Command activating changed rules, if the backend needs one.
+    fn commit(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Command enabling the inactive firewall, if the backend has one.
+    fn enable(&self) -> Option<Vec<String>>;
+}
+
+/// The backend of `kind`, or the detected one: firewalld when it is running,
+/// otherwise ufw, or raw nftables when ufw is not installed. Queries run
+/// with `elevator`.
+pub(crate) fn select(kind: Option<Kind>, elevator: Elevator) -> Box<dyn Backend> {
+    let running = firewalld::running();
+    let kind = kind.unwrap_or(if running {
+        Kind::Firewalld
+    } else if on_path("ufw") || !on_path("nft") {
+        Kind::Ufw
+    } else {
+        Kind::Nftables
+    });
+    match kind {
+        Kind::Ufw => Box::new(ufw::Ufw { elevator }),
+        Kind::Firewalld => Box::new(firewalld::Firewalld { running, elevator }),
+        Kind::Nftables => Box::new(nftables::Nftables { elevator }),
+    }
+}
+
+/// Output of a read-only query run as root.
+fn query(elevator: Elevator, args: &[&str]) -> Result<String, InstallError> {
+    command_output(Command::new(elevator.name()).args(args))
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
@@ -0,0 +1,234 @@
+use std::process::Command;
+
+use crate::install::InstallError;
+use crate::privilege::Elevator;
+
+use super::super::rules::{Action, Direction, Policies, Policy, Rule};
+use super::{args, Backend, Installed, Snapshot};
+
+/// Table holding every chain and rule hyde-ext manages.
+const TABLE: [&str; 2] = ["inet", "hyde_ext"];
+
+/// Rules created with the table so that replies, loopback traffic, ICMPv6
+/// (neighbor discovery and router advertisements, without which IPv6 stops
+/// working) and ping pass.
+const BUILTIN: [&str; 4] = [
+    "input ct state established,related accept",
+    "input iifname \"lo\" accept",
+    "input meta l4proto ipv6-icmp accept",
+    "input icmp type echo-request accept",
+];
+
+/// Saves the running table where `nftables.service` loads it at boot. The
+/// first two lines make loading replace an existing table instead of adding
+/// its rules a second time.
+const PERSIST: &str = "mkdir -p /etc/nftables.d && { echo 'table inet hyde_ext'; echo 'delete table inet hyde_ext'; \
+                       nft list table inet hyde_ext; } > /etc/nftables.d/hyde_ext.nft";
+
+/// Includes the saved tables from `/etc/nftables.conf`, which does not read
+/// `/etc/nftables.d` by default, and enables the service loading it at boot.
+/// The service is not started: that would flush the running ruleset.
+const ENABLE: &str = "grep -qxF 'include \"/etc/nftables.d/*.nft\"' /etc/nftables.conf \
+                      || echo 'include \"/etc/nftables.d/*.nft\"' >> /etc/nftables.conf; \
+                      systemctl enable nftables.service";
+
+/// Raw nftables: the rules live in an `inet hyde_ext` table of their own and
+/// are compared in the form `nft -a list table` prints them. The table is
+/// saved to `/etc/nftables.d/hyde_ext.nft` for `nftables.service`.
+pub(crate) struct Nftables {
+    pub elevator: Elevator,
+}
+
+impl Backend for Nftables {
+    fn name(&self) -> &'static str {
+        "nftables"
+    }
+
+    fn snapshot(&self, policies: Policies) -> Result<Snapshot, InstallError> {
+        // Routed traffic is always dropped unless a rule accepts it.
+        let chains = [
+            ("input", verdict(policies.incoming)?),
+            ("forward", "drop"),
+            ("output", verdict(policies.outgoing)?),
+        ];
+        let mut command = args(&["nft", "-a", "list", "table"]);
+        command.extend(args(&TABLE));
+        let output = Command::new(self.elevator.name()).args(&command).output().map_err(|e| {
+            InstallError::not_started(format!("{} nft -a list table", self.elevator.name()), e)
+        })?;
+        let builtin = BUILTIN.iter().map(|rule| rule.to_string()).collect();
+
+        // A missing table is reported as an error; it is created first.
+        // Without the service it would be gone after a reboot.
+        let active = output.status.success() && service_enabled();
+        if !output.status.success() {
+            let mut pending = vec![table_command("add", "table", &[])];
+            for (chain, policy) in chains {
+                let definition = format!("{{ type filter hook {} priority 0; policy {}; }}", chain, policy);
+                pending.push(table_command("add", "chain", &[chain, &definition]));
+            }
+            for rule in BUILTIN {
+                let tokens: Vec<&str> = rule.split(' ').collect();
+                pending.push(table_command("add", "rule", &tokens));
+            }
+            return Ok(Snapshot { active: false, pending, rules: Vec::new(), builtin });
+        }
+
+        let listed = String::from_utf8_lossy(&output.stdout);
+        let current = parse_policies(&listed);
+        let pending = chains
+            .into_iter()
+            .filter(|(chain, policy)| current.iter().any(|(name, current)| name == chain && current != policy))
+            .map(|(chain, policy)| {
+                let mut command = args(&["nft", "chain"]);
+                command.extend(args(&TABLE));
+                command.extend([chain.to_string(), format!("{{ policy {}; }}", policy)]);
+                command
+            })
+            .collect();
+        Ok(Snapshot { active, pending, rules: parse_table(&listed), builtin })
+    }
+
+    fn key(&self, rule: &Rule) -> Result<String, String> {
+        if rule.app.is_some() {
+            return Err("nftables has no application profiles".to_string());
+        }
+        let (chain, interface) = match rule.direction {
+            Direction::In => ("input", "iifname"),
+            Direction::Out => ("output", "oifname"),
+        };
+        let mut tokens = vec![chain.to_string()];
+        if let Some(name) = &rule.interface {
+            tokens.push(format!("{} \"{}\"", interface, name));
+        }
+        for (address, field) in [(&rule.from, "saddr"), (&rule.to, "daddr")] {
+            if let Some(address) = address {
+                let family = if address.contains(':') { "ip6" } else { "ip" };
+                tokens.push(format!("{} {} {}", family, field, address));
+            }
+        }
+
+        // Service names are resolved to numbers, as `nft list` prints them.
+        let key = rule.key().ok_or_else(|| format!("'{}' is not a valid rule", rule))?;
+        if let Some(port) = key.port() {
+            let port = if port.contains(',') {
+                format!("{{ {} }}", port.split(',').collect::<Vec<_>>().join(", "))
+            } else {
+                port.replace(':', "-")
+            };
+            match key.proto() {
+                Some(proto) => tokens.push(format!("{} dport {}", proto, port)),
+                None => tokens.push(format!("meta l4proto {{ tcp, udp }} th dport {}", port)),
+            }
+        }
+        tokens.push(
+            match rule.action {
+                Action::Allow => "accept",
+                Action::Deny => "drop",
+                Action::Reject => "reject",
+                Action::Limit => return Err("limit is not supported by nftables, use allow".to_string()),
+            }
+            .to_string(),
+        );
+        Ok(tokens.join(" "))
+    }
+
+    fn add(&self, rule: &Rule) -> Result<Vec<String>, String> {
+        let key = self.key(rule)?;
+        let mut tokens: Vec<&str> = key.split(' ').collect();
+        let comment = rule.comment.as_ref().map(|comment| format!("\"{}\"", comment.replace('"', "'")));
+        if let Some(comment) = &comment {
+            tokens.extend(["comment", comment]);
+        }
+        Ok(table_command("add", "rule", &tokens))
+    }
+
+    fn remove(&self, rule: &Installed) -> Vec<String> {
+        table_command("delete", "rule", &[&rule.handle[0], "handle", &rule.handle[1]])
+    }
+
+    fn commit(&self) -> Option<Vec<String>> {
+        // Changes are live at once, only the saved table is updated.
+        Some(args(&["sh", "-c", PERSIST]))
+    }
+
+    fn enable(&self) -> Option<Vec<String>> {
+        Some(args(&["sh", "-c", &format!("{} && {}", PERSIST, ENABLE)]))
+    }
+}
+
+fn service_enabled() -> bool {
+    Command::new("systemctl")
+        .args(["is-enabled", "--quiet", "nftables.service"])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// The chain policy for `policy`; nftables chains cannot reject.
+fn verdict(policy: Policy) -> Result<&'static str, InstallError> {
+    match policy {
+        Policy::Allow => Ok("accept"),
+        Policy::Deny => Ok("drop"),
+        Policy::Reject => {
+            Err(InstallError::Conflict("nftables chains cannot reject by policy, use deny".to_string()))
+        }
+    }
+}
+
+/// `nft <verb> <object> inet hyde_ext <args>`.
+fn table_command(verb: &str, object: &str, rest: &[&str]) -> Vec<String> {
+    let mut command = args(&["nft", verb, object]);
+    command.extend(args(&TABLE));
+    command.extend(args(rest));
+    command
+}
+
+/// Parses the rules of `nft -a list table inet hyde_ext`:
+///
+/// ```text
+/// table inet hyde_ext { # handle 5
+///     chain input { # handle 1
+///         type filter hook input priority filter; policy drop;
+///         tcp dport 443 accept comment "HTTPS" # handle 6
+///     }
+/// }
+/// ```
+fn parse_table(output: &str) -> Vec<Installed> {
+    let mut rules = Vec::new();
+    let mut chain = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("chain ") {
+            chain = rest.split_whitespace().next().map(str::to_string);
+            continue;
+        }
+        let (Some(chain), Some((rule, handle))) = (&chain, line.rsplit_once(" # handle ")) else {
+            continue;
+        };
+        if rule.starts_with("table ") {
+            continue;
+        }
+        let rule = rule.split(" comment \"").next().unwrap_or(rule);
+        let key = format!("{} {}", chain, rule);
+        rules.push(Installed { key: Some(key.clone()), display: key, handle: vec![chain.clone(), handle.to_string()] });
+    }
+    rules
+}
+
+/// The `policy` of every chain in `nft list table`, as `(chain, policy)`.
+fn parse_policies(output: &str) -> Vec<(String, String)> {
+    let mut policies = Vec::new();
+    let mut chain = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("chain ") {
+            chain = rest.split_whitespace().next().map(str::to_string);
+            continue;
+        }
+        let Some(chain) = &chain else {
+            continue;
+        };
+        if let Some(policy) = line.split("policy ").nth(1).and_then(|rest| rest.split(';').next()) {
+            policies.push((chain.clone(), policy.trim().to_string()));
+        }
+    }
+    policies
+}
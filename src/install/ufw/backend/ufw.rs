@@ -0,0 +1,72 @@
+use crate::install::InstallError;
+use crate::privilege::Elevator;
+
+use super::super::rules::{Policies, Rule, RuleKey};
+use super::super::status;
+use super::{args, query, Backend, Installed, Snapshot};
+
+const LOGGING: &str = "medium";
+
+/// Uncomplicated Firewall. Rules are compared in their normalized `ufw`
+/// syntax, read back with `ufw show added`.
+pub(crate) struct Ufw {
+    pub elevator: Elevator,
+}
+
+impl Backend for Ufw {
+    fn name(&self) -> &'static str {
+        "ufw"
+    }
+
+    fn snapshot(&self, policies: Policies) -> Result<Snapshot, InstallError> {
+        let status = status::parse_status(&query(self.elevator, &["ufw", "status", "verbose"])?);
+        let added = status::parse_added(&query(self.elevator, &["ufw", "show", "added"])?);
+
+        let mut pending = Vec::new();
+        let defaults =
+            [("incoming", policies.incoming.name()), ("outgoing", policies.outgoing.name()), ("routed", "deny")];
+        for (direction, policy) in defaults {
+            if status.policy(direction) != Some(policy) {
+                pending.push(args(&["ufw", "default", policy, direction]));
+            }
+        }
+        if status.logging.as_deref() != Some(LOGGING) {
+            pending.push(args(&["ufw", "logging", "on", LOGGING]));
+        }
+
+        let rules = added
+            .into_iter()
+            .map(|rule| Installed {
+                key: RuleKey::parse(&rule).map(|key| key.to_string()),
+                display: rule.join(" "),
+                handle: rule,
+            })
+            .collect();
+        Ok(Snapshot { active: status.active, pending, rules, builtin: Vec::new() })
+    }
+
+    fn key(&self, rule: &Rule) -> Result<String, String> {
+        rule.key().map(|key| key.to_string()).ok_or_else(|| format!("'{}' is not a valid ufw rule", rule))
+    }
+
+    fn normalize(&self, key: &str) -> Option<String> {
+        RuleKey::parse(&status::split_args(key)).map(|key| key.to_string())
+    }
+
+    fn add(&self, rule: &Rule) -> Result<Vec<String>, String> {
+        let mut command = args(&["ufw"]);
+        command.extend(rule.args());
+        Ok(command)
+    }
+
+    fn remove(&self, rule: &Installed) -> Vec<String> {
+        let mut command = args(&["ufw", "delete"]);
+        command.extend(rule.handle.iter().cloned());
+        command
+    }
+
+    fn enable(&self) -> Option<Vec<String>> {
+        // `--force` skips ufw's own prompt; the handler checks SSH access instead.
+        Some(args(&["ufw", "--force", "enable"]))
+    }
+}
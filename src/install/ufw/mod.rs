@@ -0,0 +1,335 @@
+mod backend;
+mod rules;
+mod status;
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process::Command;
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+
+use crate::context::Context;
+use crate::install::{command_output, describe, manager, FileEntry, InstallError, Outcome};
+use crate::state::{Record, State};
+
+use backend::Installed;
+use rules::{Catalog, Policies, Rule};
+
+pub(crate) fn install(ctx: &Context, choice: &FileEntry) -> Result<Outcome, InstallError> {
+    ctx.log.action("Installing", "UFW setup tailored for standard users and developers.");
+
+    let catalog = load_catalog(ctx)?;
+    let backend = backend::select(catalog.firewall.backend, ctx.elevator);
+    ctx.log.debug(format!("Configuring the firewall with {}.", backend.name()));
+    let (rules, policies) = match &ctx.firewall.profile {
+        Some(name) => profile_rules(ctx, &catalog, name)?,
+        None => (select_rules(ctx, catalog.rules.clone())?, Policies::default()),
+    };
+
+    let mut keys = Vec::new();
+    for rule in &rules {
+        let key = backend.key(rule).map_err(|e| {
+            InstallError::Conflict(format!("Rule '{}' cannot be applied with {}: {}", rule.name, backend.name(), e))
+        })?;
+        keys.push(key);
+    }
+    // Rules the backend cannot express are never on the system either.
+    let known: Vec<(String, &Rule)> =
+        catalog.rules.iter().filter_map(|rule| backend.key(rule).ok().map(|key| (key, rule))).collect();
+
+    let snapshot = backend.snapshot(policies)?;
+
+    // Rules hyde-ext added in earlier runs; only these are removed when they
+    // are no longer selected. A profile removes every other catalog rule.
+    let previous: HashSet<String> = State::load()
+        .ok()
+        .and_then(|state| state.get(&choice.title).map(|record| record.firewall_rules.clone()))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|key| backend.normalize(key))
+        .collect();
+    let selected: HashSet<&String> = keys.iter().collect();
+    let is_known = |key: &String| known.iter().any(|(known, _)| known == key);
+
+    let unknown: Vec<&str> = snapshot
+        .rules
+        .iter()
+        .filter(|rule| rule.key.as_ref().is_none_or(|key| !is_known(key) && !snapshot.builtin.contains(key)))
+        .map(|rule| rule.display.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        ctx.log.warn(format!("Rules not declared in UFW.toml are left in place: {}", unknown.join(", ")));
+    }
+    let (removed, kept): (Vec<&Installed>, Vec<&Installed>) = snapshot.rules.iter().partition(|rule| {
+        rule.key.as_ref().is_some_and(|key| {
+            is_known(key) && !selected.contains(key) && (previous.contains(key) || ctx.firewall.profile.is_some())
+        })
+    });
+    let present: HashSet<&String> = kept.iter().filter_map(|rule| rule.key.as_ref()).collect();
+
+    // Check the rules the firewall ends up with before changing anything.
+    let remaining = known.iter().filter(|(key, _)| present.contains(key)).map(|(_, rule)| *rule).chain(&rules);
+    check_ssh_access(ctx, remaining)?;
+
+    let mut changed = false;
+    for command in &snapshot.pending {
+        run(ctx, command)?;
+        ctx.log.detail(command.join(" "));
+        changed = true;
+    }
+
+    // Rules hyde-ext added that are still in place, recorded before anything
+    // can fail; a rule whose removal fails stays recorded too.
+    let mut managed: Vec<String> =
+        keys.iter().filter(|key| present.contains(key) && previous.contains(*key)).cloned().collect();
+    let mut failed = Vec::new();
+    for (index, rule) in removed.iter().enumerate() {
+        ctx.log.action("Removing", &rule.display);
+        match run(ctx, &backend.remove(rule)) {
+            Ok(()) => changed = true,
+            Err(e) => {
+                ctx.log.error(&e);
+                failed.push(rule.display.clone());
+                if ctx.fail_fast {
+                    let remaining = removed[index..].iter().filter_map(|rule| rule.key.clone());
+                    managed.extend(remaining.filter(|key| previous.contains(key)));
+                    let record = Record { firewall_rules: managed, ..Record::default() };
+                    return Err(InstallError::Partial { failed, record: Some(Box::new(record)) });
+                }
+                managed.extend(rule.key.clone().filter(|key| previous.contains(key)));
+            }
+        }
+    }
+
+    ctx.log.action("Allowing", format!("{} selected rule(s)...", rules.len()));
+    for (rule, key) in rules.iter().zip(&keys) {
+        if present.contains(key) {
+            ctx.log.skip(format!("{}: {} is already in place", rule.name, rule));
+            continue;
+        }
+        let result = backend.add(rule).map_err(InstallError::Conflict).and_then(|command| run(ctx, &command));
+        match result {
+            Ok(()) => {
+                ctx.log.detail(format!("{}: {}", rule.name, rule));
+                managed.push(key.clone());
+                changed = true;
+            }
+            Err(e) => {
+                ctx.log.error(format!("Failed to apply {}: {}", rule.name, e));
+                failed.push(rule.name.clone());
+                if ctx.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        ctx.log.warn(format!("{} is not enabled because some rules could not be applied.", backend.name()));
+        let record = Record { firewall_rules: managed, ..Record::default() };
+        return Err(InstallError::Partial { failed, record: Some(Box::new(record)) });
+    }
+    if changed {
+        if let Some(command) = backend.commit() {
+            run(ctx, &command)?;
+        }
+    }
+
+    match backend.enable() {
+        Some(command) if !snapshot.active => {
+            ctx.log.action("Enabling", format!("{}...", backend.name()));
+            run(ctx, &command)?;
+            changed = true;
+        }
+        _ => ctx.log.debug(format!("{} is already active.", backend.name())),
+    }
+
+    if !changed {
+        return Ok(Outcome::Skipped(format!("The firewall is already configured, {} rule(s) in place.", rules.len())));
+    }
+
+    ctx.log.success(format!("applied {} configuration.", backend.name()));
+    Ok(Outcome::Applied(Box::new(Record { firewall_rules: managed, ..Record::default() })))
+}
+
+/// Removes the firewall rules recorded in `record` that are still on the
+/// system. Rules whose removal fails stay recorded.
+pub(crate) fn uninstall(ctx: &Context, record: &Record) -> Result<Outcome, InstallError> {
+    let catalog = load_catalog(ctx)?;
+    let backend = backend::select(catalog.firewall.backend, ctx.elevator);
+    let snapshot = backend.snapshot(Policies::default())?;
+
+    let recorded: HashSet<String> = record.firewall_rules.iter().filter_map(|key| backend.normalize(key)).collect();
+    let removed: Vec<&Installed> =
+        snapshot.rules.iter().filter(|rule| rule.key.as_ref().is_some_and(|key| recorded.contains(key))).collect();
+    if removed.is_empty() {
+        ctx.log.info("None of the firewall rules added by hyde-ext are present anymore.");
+        return Ok(Outcome::Applied(Box::new(record.clone())));
+    }
+
+    let mut remaining = Vec::new();
+    let mut failed = Vec::new();
+    for (index, rule) in removed.iter().enumerate() {
+        ctx.log.action("Removing", &rule.display);
+        if let Err(e) = run(ctx, &backend.remove(rule)) {
+            ctx.log.error(&e);
+            failed.push(rule.display.clone());
+            remaining.extend(rule.key.clone());
+            if ctx.fail_fast {
+                remaining.extend(removed[index + 1..].iter().filter_map(|rule| rule.key.clone()));
+                break;
+            }
+        }
+    }
+    if failed.len() < removed.len() {
+        if let Some(command) = backend.commit() {
+            run(ctx, &command)?;
+        }
+    }
+    if !failed.is_empty() {
+        let record = Record { firewall_rules: remaining, ..Record::default() };
+        return Err(InstallError::Partial { failed, record: Some(Box::new(record)) });
+    }
+
+    ctx.log.success(format!("removed {} firewall rule(s)", removed.len()));
+    Ok(Outcome::Applied(Box::new(record.clone())))
+}
+
+/// `hyde-ext firewall switch <profile>`: runs the firewall entries of the
+/// catalog with the rules of the profile in `ctx.firewall.profile`.
+pub fn switch_profile(ctx: &Context) {
+    let entries: Vec<FileEntry> = manager::catalog(ctx).into_iter().filter(|entry| entry.handler == "ufw").collect();
+    if entries.is_empty() {
+        let e = InstallError::MissingPath("No firewall entry found in the catalog.".to_string());
+        ctx.log.error(&e);
+        ctx.report.failed("firewall", Some("ufw"), e.kind(), e.to_string());
+        return;
+    }
+    manager::install_entries(ctx, &entries);
+}
+
+/// The rules and default policies of the profile called `name`.
+fn profile_rules(ctx: &Context, catalog: &Catalog, name: &str) -> Result<(Vec<Rule>, Policies), InstallError> {
+    let Some(profile) = catalog.profile(name) else {
+        let available: Vec<&str> = catalog.profiles.iter().map(|profile| profile.name.as_str()).collect();
+        return Err(InstallError::Selection(format!(
+            "No firewall profile matches '{}'. Available profiles: {}",
+            name,
+            available.join(", ")
+        )));
+    };
+    match &profile.description {
+        Some(description) => ctx.log.info(format!("Switching to the {} profile: {}", profile.name, description)),
+        None => ctx.log.info(format!("Switching to the {} profile.", profile.name)),
+    }
+    let rules = catalog
+        .rules
+        .iter()
+        .filter(|rule| profile.rules.iter().any(|name| rule.name.eq_ignore_ascii_case(name)))
+        .cloned()
+        .collect();
+    let policies = profile.policies();
+    ctx.log.debug(format!(
+        "Default policies: {} incoming, {} outgoing.",
+        policies.incoming.name(),
+        policies.outgoing.name()
+    ));
+    Ok((rules, policies))
+}
+
+/// Refuses to change the firewall from an SSH session when none of the rules
+/// it ends up with allows the port of the session, unless `--force` is given.
+fn check_ssh_access<'a>(ctx: &Context, mut rules: impl Iterator<Item = &'a Rule>) -> Result<(), InstallError> {
+    // `SSH_CONNECTION` is `<client address> <client port> <server address> <server port>`.
+    let Ok(connection) = env::var("SSH_CONNECTION") else {
+        return Ok(());
+    };
+    let port = connection.split_whitespace().nth(3).unwrap_or("22");
+    if rules.any(|rule| rule.key().is_some_and(|key| key.allows_port(port))) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "No firewall rule allows port {} of the current SSH session, enabling the firewall would lock it out.",
+        port
+    );
+    if ctx.force {
+        ctx.log.warn(format!("{} Continuing because of --force.", message));
+        return Ok(());
+    }
+    Err(InstallError::Conflict(format!("{} Select the ssh rule or use --force.", message)))
+}
+
+/// Runs `command` as root and waits for it. The output goes to the run log,
+/// stderr is part of the error when the command fails.
+fn run(ctx: &Context, args: &[String]) -> Result<(), InstallError> {
+    let mut command = Command::new(ctx.elevator.name());
+    command.args(args);
+    ctx.log.debug(format!("Running {}", describe(&command)));
+    for line in command_output(&mut command)?.lines() {
+        ctx.log.capture(line);
+    }
+    Ok(())
+}
+
+/// Reads the `[firewall]` and `[[rule]]` tables of `UFW.toml` in the assets directory.
+fn load_catalog(ctx: &Context) -> Result<Catalog, InstallError> {
+    let path = ctx.assets_dir.join("UFW").join("UFW.toml");
+    let content = fs::read_to_string(&path)
+        .map_err(|e| InstallError::io(format!("Failed to read firewall rules {}", path.display()), e))?;
+    let catalog = rules::parse(&content)
+        .map_err(|e| InstallError::Parse(format!("Invalid firewall rules {}: {}", path.display(), e)))?;
+    Ok(catalog)
+}
+
+/// Picks the rules to apply: the ones named with `--firewall-rules`,
+/// interactively, or the default rules when stdin is not a terminal.
+fn select_rules(ctx: &Context, rules: Vec<Rule>) -> Result<Vec<Rule>, InstallError> {
+    if !ctx.firewall.rules.is_empty() {
+        for name in &ctx.firewall.rules {
+            if !rules.iter().any(|rule| rule.name.eq_ignore_ascii_case(name)) {
+                let available: Vec<&str> = rules.iter().map(|rule| rule.name.as_str()).collect();
+                return Err(InstallError::Selection(format!(
+                    "No firewall rule matches '{}'. Available rules: {}",
+                    name,
+                    available.join(", ")
+                )));
+            }
+        }
+        return Ok(rules
+            .into_iter()
+            .filter(|rule| ctx.firewall.rules.iter().any(|name| rule.name.eq_ignore_ascii_case(name)))
+            .collect());
+    }
+    if !io::stdin().is_terminal() || ctx.assume_yes || rules.is_empty() {
+        return Ok(rules.into_iter().filter(|rule| rule.default).collect());
+    }
+
+    let items: Vec<String> = rules.iter().map(rule_label).collect();
+    let defaults: Vec<bool> = rules.iter().map(|rule| rule.default).collect();
+    let indices = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{}\n",
+            "Select firewall rules:\n [a] All   [space] Toggle   [enter] Confirm".yellow()
+        ))
+        .items(&items)
+        .defaults(&defaults)
+        .interact_opt()
+        .unwrap_or_else(|e| {
+            ctx.log.error(format!("Failed to select options: {}", e));
+            None
+        })
+        .unwrap_or_default();
+    Ok(indices.into_iter().map(|index| rules[index].clone()).collect())
+}
+
+fn rule_label(rule: &Rule) -> String {
+    let mut label = format!("{} ({})", rule.name, rule);
+    if let Some(comment) = &rule.comment {
+        label.push_str(&format!(" - {}", comment));
+    }
+    label
+}
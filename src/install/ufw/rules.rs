@@ -0,0 +1,473 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+
+use serde::{Deserialize, Deserializer};
+
+use super::backend::Kind;
+
+/// The `[firewall]`, `[[rule]]` and `[[profile]]` tables of `UFW.toml`. The `[[file]]`
+/// catalog entries of the same file are read by the install manager and
+/// ignored here.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Catalog {
+    #[serde(default)]
+    pub firewall: Settings,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+    #[serde(default, rename = "profile")]
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Settings {
+    /// Firewall to configure; detected when `None`.
+    #[serde(default)]
+    pub backend: Option<Kind>,
+}
+
+/// A named set of rules for one kind of network, applied with
+/// `hyde-ext firewall switch <name>`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Names of the `[[rule]]` tables allowed in this profile; every other
+    /// rule of the catalog is removed.
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// Policy for incoming traffic no rule matches; deny when `None`.
+    #[serde(default)]
+    pub incoming: Option<Policy>,
+    /// Policy for outgoing traffic no rule matches; allow when `None`.
+    #[serde(default)]
+    pub outgoing: Option<Policy>,
+}
+
+/// What happens to traffic no rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Policy {
+    Allow,
+    Deny,
+    Reject,
+}
+
+/// Default policies of the firewall: incoming traffic is denied and outgoing
+/// traffic allowed unless a profile says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Policies {
+    pub incoming: Policy,
+    pub outgoing: Policy,
+}
+
+impl Default for Policies {
+    fn default() -> Self {
+        Policies { incoming: Policy::Deny, outgoing: Policy::Allow }
+    }
+}
+
+impl Catalog {
+    /// The profile called `name` (case-insensitive).
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Profile {
+    pub fn policies(&self) -> Policies {
+        let default = Policies::default();
+        Policies {
+            incoming: self.incoming.unwrap_or(default.incoming),
+            outgoing: self.outgoing.unwrap_or(default.outgoing),
+        }
+    }
+}
+
+/// One firewall rule: either a port (with an optional protocol) or a ufw
+/// application profile, optionally narrowed to addresses and an interface.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Rule {
+    /// Identifier used by `install --firewall-rules`.
+    pub name: String,
+    /// Port number, range (`6000:6007`) or service name (`ssh`).
+    #[serde(default, deserialize_with = "port")]
+    pub port: Option<String>,
+    #[serde(default)]
+    pub proto: Option<Protocol>,
+    /// Application profile from `ufw app list` (e.g. `OpenSSH`).
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub action: Action,
+    #[serde(default)]
+    pub direction: Direction,
+    /// Source address or CIDR, any when `None`.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Destination address or CIDR, any when `None`.
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Stored with the rule by ufw and shown in the picker.
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Selected in the picker and applied without prompting.
+    #[serde(default = "enabled")]
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Action {
+    #[default]
+    Allow,
+    Deny,
+    Reject,
+    /// Allow, but deny addresses with too many connection attempts.
+    Limit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Direction {
+    #[default]
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+            Action::Reject => "reject",
+            Action::Limit => "limit",
+        }
+    }
+}
+
+impl Policy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Policy::Allow => "allow",
+            Policy::Deny => "deny",
+            Policy::Reject => "reject",
+        }
+    }
+}
+
+impl Direction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+        }
+    }
+}
+
+impl Protocol {
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Accepts `port = 443` as well as `port = "443"` or `port = "6000:6007"`.
+fn port<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Port {
+        Number(u16),
+        Text(String),
+    }
+
+    Ok(Option::<Port>::deserialize(deserializer)?.map(|port| match port {
+        Port::Number(number) => number.to_string(),
+        Port::Text(text) => text,
+    }))
+}
+
+impl Rule {
+    /// `443/tcp`, `ssh` or the application profile name.
+    pub fn target(&self) -> String {
+        match (&self.port, self.proto, &self.app) {
+            (Some(port), Some(proto), _) => format!("{}/{}", port, proto.name()),
+            (Some(port), None, _) => port.clone(),
+            (None, _, Some(app)) => app.clone(),
+            (None, _, None) => String::new(),
+        }
+    }
+
+    /// Arguments of `ufw` adding this rule. Rules without addresses or an
+    /// interface use the short form (`allow 443/tcp`), the others the full
+    /// `from ... to ... port ...` syntax.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![self.action.name().to_string()];
+        if self.from.is_none() && self.to.is_none() && self.interface.is_none() {
+            if self.direction == Direction::Out {
+                args.push("out".to_string());
+            }
+            args.push(self.target());
+        } else {
+            args.push(self.direction.name().to_string());
+            if let Some(interface) = &self.interface {
+                args.extend(["on".to_string(), interface.clone()]);
+            }
+            if let Some(proto) = self.proto {
+                args.extend(["proto".to_string(), proto.name().to_string()]);
+            }
+            args.extend(["from".to_string(), self.from.clone().unwrap_or_else(|| "any".to_string())]);
+            args.extend(["to".to_string(), self.to.clone().unwrap_or_else(|| "any".to_string())]);
+            match (&self.port, &self.app) {
+                (Some(port), _) => args.extend(["port".to_string(), port.clone()]),
+                (None, Some(app)) => args.extend(["app".to_string(), app.clone()]),
+                (None, None) => {}
+            }
+        }
+        if let Some(comment) = &self.comment {
+            args.extend(["comment".to_string(), comment.clone()]);
+        }
+        args
+    }
+
+    pub fn key(&self) -> Option<RuleKey> {
+        RuleKey::parse(&self.args())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match (&self.port, &self.app) {
+            (Some(_), Some(_)) => Err(format!("rule '{}' sets both port and app", self.name)),
+            (None, None) => Err(format!("rule '{}' needs a port or an app", self.name)),
+            (None, Some(_)) if self.proto.is_some() => {
+                Err(format!("rule '{}' sets proto for an application profile", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    /// The rule as written after `ufw`, e.g. `allow in on wg0 from 10.0.0.0/8 to any port 22`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self
+            .args()
+            .into_iter()
+            .map(|arg| if arg.contains(' ') { format!("'{}'", arg) } else { arg })
+            .collect();
+        write!(f, "{}", args.join(" "))
+    }
+}
+
+/// Normalized form of a rule, used to compare catalog rules with the rules
+/// reported by `ufw show added`: omitted addresses become `any`, the keyword
+/// order and the comment are ignored and service names are resolved to ports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct RuleKey {
+    route: bool,
+    action: String,
+    direction: String,
+    interface: Option<String>,
+    proto: Option<String>,
+    from: String,
+    from_port: Option<String>,
+    to: String,
+    port: Option<String>,
+    app: Option<String>,
+}
+
+impl RuleKey {
+    /// Destination port, a number, range or comma separated list.
+    pub fn port(&self) -> Option<&str> {
+        self.port.as_deref()
+    }
+
+    pub fn proto(&self) -> Option<&str> {
+        self.proto.as_deref()
+    }
+
+    /// Parses the arguments of a `ufw` rule command in the short
+    /// (`allow 443/tcp`) or the full (`allow from ... to ... port ...`) form.
+    pub fn parse(args: &[String]) -> Option<RuleKey> {
+        let mut tokens = args.iter().map(String::as_str).peekable();
+        let mut key = RuleKey { direction: "in".to_string(), ..RuleKey::default() };
+        if tokens.peek() == Some(&"route") {
+            key.route = true;
+            tokens.next();
+        }
+        key.action = tokens.next().filter(|action| ["allow", "deny", "reject", "limit"].contains(action))?.to_string();
+        key.from = "any".to_string();
+        key.to = "any".to_string();
+        if let Some(direction) = tokens.next_if(|token| *token == "in" || *token == "out") {
+            key.direction = direction.to_string();
+        }
+        if tokens.next_if_eq(&"on").is_some() {
+            key.interface = Some(tokens.next()?.to_string());
+        }
+        tokens.next_if(|token| *token == "log" || *token == "log-all");
+
+        if !matches!(tokens.peek(), Some(&("proto" | "from" | "to"))) {
+            // `443/tcp`, a port or service name, or an application profile.
+            let target = tokens.next()?;
+            match target.split_once('/') {
+                Some((port, proto)) => {
+                    key.port = Some(port.to_string());
+                    key.proto = Some(proto.to_string());
+                }
+                None => key.port = Some(target.to_string()),
+            }
+        }
+        let mut from_side = false;
+        while let Some(token) = tokens.next() {
+            match token {
+                "proto" => key.proto = Some(tokens.next()?.to_string()),
+                "from" => {
+                    key.from = address(tokens.next()?);
+                    from_side = true;
+                }
+                "to" => {
+                    key.to = address(tokens.next()?);
+                    from_side = false;
+                }
+                "port" if from_side => key.from_port = Some(tokens.next()?.to_string()),
+                "port" => key.port = Some(tokens.next()?.to_string()),
+                "app" => key.app = Some(tokens.next()?.to_string()),
+                "comment" => {
+                    tokens.next();
+                }
+                _ => {}
+            }
+        }
+        Some(key.resolve())
+    }
+
+    /// Whether the rule lets incoming connections to `port` through.
+    pub fn allows_port(&self, port: &str) -> bool {
+        if self.route || self.direction != "in" || !matches!(self.action.as_str(), "allow" | "limit") {
+            return false;
+        }
+        if self.proto.as_deref().is_some_and(|proto| proto != "tcp") {
+            return false;
+        }
+        match (&self.port, &self.app) {
+            (Some(ports), _) => ports.split(',').any(|part| match part.split_once(':') {
+                Some((low, high)) => {
+                    let port = port.parse::<u16>().ok();
+                    let (low, high) = (low.parse::<u16>().ok(), high.parse::<u16>().ok());
+                    matches!((port, low, high), (Some(port), Some(low), Some(high)) if (low..=high).contains(&port))
+                }
+                None => part == port,
+            }),
+            (None, Some(app)) => port == "22" && app.to_lowercase().contains("ssh"),
+            (None, None) => true,
+        }
+    }
+
+    /// Replaces a service name in `port` by its number from `/etc/services`,
+    /// taking the protocol as well when the service only has one. Names that
+    /// are not services are application profiles.
+    fn resolve(mut self) -> RuleKey {
+        let Some(port) = self.port.clone() else {
+            return self;
+        };
+        if port.chars().all(|c| c.is_ascii_digit() || c == ':' || c == ',') {
+            return self;
+        }
+        let services = fs::read_to_string("/etc/services").unwrap_or_default();
+        let entries: Vec<(&str, &str)> = services
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .filter(|line| line.split_whitespace().any(|name| name == port))
+            .filter_map(|line| line.split_whitespace().nth(1)?.split_once('/'))
+            .collect();
+        match entries.first() {
+            Some((number, proto)) => {
+                self.port = Some(number.to_string());
+                if self.proto.is_none() && entries.iter().all(|(_, other)| other == proto) {
+                    self.proto = Some(proto.to_string());
+                }
+            }
+            None => {
+                self.port = None;
+                self.app = Some(port);
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for RuleKey {
+    /// The full `ufw` syntax of the key, which [`RuleKey::parse`] reads back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.route {
+            write!(f, "route ")?;
+        }
+        write!(f, "{} {}", self.action, self.direction)?;
+        if let Some(interface) = &self.interface {
+            write!(f, " on {}", interface)?;
+        }
+        if let Some(proto) = &self.proto {
+            write!(f, " proto {}", proto)?;
+        }
+        write!(f, " from {}", self.from)?;
+        if let Some(port) = &self.from_port {
+            write!(f, " port {}", port)?;
+        }
+        write!(f, " to {}", self.to)?;
+        if let Some(port) = &self.port {
+            write!(f, " port {}", port)?;
+        }
+        if let Some(app) = &self.app {
+            write!(f, " app '{}'", app)?;
+        }
+        Ok(())
+    }
+}
+
+fn address(address: &str) -> String {
+    match address {
+        "0.0.0.0/0" | "::/0" | "Anywhere" => "any".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Parses the rules of `UFW.toml` and checks that every rule has a target and
+/// a unique name, and that profiles only name declared rules.
+pub(crate) fn parse(content: &str) -> Result<Catalog, String> {
+    let catalog: Catalog = toml::from_str(content).map_err(|e| e.message().to_string())?;
+    let mut names = HashSet::new();
+    for rule in &catalog.rules {
+        rule.validate()?;
+        if !names.insert(rule.name.to_lowercase()) {
+            return Err(format!("rule '{}' is declared twice", rule.name));
+        }
+    }
+    let mut profiles = HashSet::new();
+    for profile in &catalog.profiles {
+        if !profiles.insert(profile.name.to_lowercase()) {
+            return Err(format!("profile '{}' is declared twice", profile.name));
+        }
+        if let Some(rule) = profile.rules.iter().find(|rule| !names.contains(&rule.to_lowercase())) {
+            return Err(format!("profile '{}' names the undeclared rule '{}'", profile.name, rule));
+        }
+    }
+    Ok(catalog)
+}
@@ -0,0 +1,104 @@
+/// Firewall state from `ufw status verbose`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Status {
+    pub active: bool,
+    /// Logging level, e.g. `medium`; `None` when logging is off or unknown.
+    pub logging: Option<String>,
+    /// Default policies as `(direction, policy)`, e.g. `("incoming", "deny")`.
+    pub defaults: Vec<(String, String)>,
+}
+
+impl Status {
+    pub fn policy(&self, direction: &str) -> Option<&str> {
+        self.defaults
+            .iter()
+            .find(|(name, _)| name == direction)
+            .map(|(_, policy)| policy.as_str())
+    }
+}
+
+/// Parses the header of `ufw status verbose`:
+///
+/// ```text
+/// Status: active
+/// Logging: on (medium)
+/// Default: deny (incoming), allow (outgoing), deny (routed)
+/// ```
+pub(crate) fn parse_status(output: &str) -> Status {
+    let mut status = Status::default();
+    for line in output.lines() {
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match field.trim() {
+            "Status" => status.active = value == "active",
+            "Logging" => {
+                status.logging = value
+                    .strip_prefix("on")
+                    .map(|level| level.trim().trim_matches(['(', ')']).to_string())
+                    .filter(|level| !level.is_empty());
+            }
+            "Default" => {
+                for policy in value.split(',') {
+                    if let Some((policy, direction)) = policy.trim().split_once(' ') {
+                        let direction = direction.trim_matches(['(', ')']).to_string();
+                        status.defaults.push((direction, policy.to_string()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    status
+}
+
+/// Parses `ufw show added`, which lists the user rules as the commands that
+/// add them (also while the firewall is inactive), into their arguments:
+///
+/// ```text
+/// Added user rules (see 'ufw status' for running firewall):
+/// ufw allow 443/tcp
+/// ufw allow in on wg0 from 10.0.0.0/8 to any port 5432 proto tcp comment 'Team database'
+/// ```
+pub(crate) fn parse_added(output: &str) -> Vec<Vec<String>> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("ufw "))
+        .map(split_args)
+        .filter(|args| !args.is_empty())
+        .collect()
+}
+
+/// Splits a command line on whitespace, keeping single or double quoted
+/// parts (comments, application names) together.
+pub(crate) fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_arg = false;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
@@ -0,0 +1,293 @@
+use colored::*;
+use regex::Regex;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of run logs kept in the log directory.
+const KEPT_LOGS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const NAMES: [&'static str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    /// Maps the number of `-v` flags to a level, starting from `Info`.
+    pub fn from_verbosity(count: u8) -> Level {
+        match count {
+            0 => Level::Info,
+            1 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+/// Leveled logger shared by every command.
+///
+/// Console output is filtered by the configured level, while the per-run log
+/// file (if it could be created) receives every message without colors.
+/// When stdout is reserved for machine readable output, every console message
+/// goes to stderr instead.
+#[derive(Clone)]
+pub struct Logger {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    level: Level,
+    stderr_only: bool,
+    /// A progress line without its final newline is on the terminal.
+    progress_open: AtomicBool,
+    file: Mutex<Option<File>>,
+    path: Option<PathBuf>,
+}
+
+impl Logger {
+    pub fn new(level: Level, log_dir: Option<&Path>, stderr_only: bool) -> Logger {
+        let (file, path) = match log_dir.map(open_log_file) {
+            Some(Ok((file, path))) => (Some(file), Some(path)),
+            Some(Err(e)) => {
+                eprintln!("{} Unable to create log file: {}", ":: Warning:".yellow(), e);
+                (None, None)
+            }
+            None => (None, None),
+        };
+
+        Logger {
+            inner: Arc::new(Inner {
+                level,
+                stderr_only,
+                progress_open: AtomicBool::new(false),
+                file: Mutex::new(file),
+                path,
+            }),
+        }
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        level <= self.inner.level
+    }
+
+    /// Path of the log file written during this run.
+    pub fn path(&self) -> Option<&Path> {
+        self.inner.path.as_deref()
+    }
+
+    pub fn error(&self, msg: impl Display) {
+        self.emit(Level::Error, ":: Error:".red(), msg);
+    }
+
+    pub fn warn(&self, msg: impl Display) {
+        self.emit(Level::Warn, ":: Warning:".yellow(), msg);
+    }
+
+    pub fn info(&self, msg: impl Display) {
+        self.emit(Level::Info, ":: Info:".bright_blue(), msg);
+    }
+
+    pub fn debug(&self, msg: impl Display) {
+        self.emit(Level::Debug, ":: Debug:".blue(), msg);
+    }
+
+    pub fn trace(&self, msg: impl Display) {
+        self.emit(Level::Trace, ":: Trace:".dimmed(), msg);
+    }
+
+    /// Announces a step of the current command, e.g. `:: Installing <title>`.
+    pub fn action(&self, verb: &str, msg: impl Display) {
+        self.emit(Level::Info, format!(":: {}", verb).blue(), msg);
+    }
+
+    /// Announces that a step was skipped, e.g. `:: Skipping <title>`.
+    pub fn skip(&self, msg: impl Display) {
+        self.emit(Level::Info, ":: Skipping".yellow(), msg);
+    }
+
+    /// Reports the successful end of a step, e.g. `  -> Successfully installed ...`.
+    pub fn success(&self, msg: impl Display) {
+        self.emit(Level::Info, "  -> Successfully".green(), msg);
+    }
+
+    /// Reports additional information about the current step.
+    pub fn detail(&self, msg: impl Display) {
+        self.emit(Level::Info, "  ->".blue(), msg);
+    }
+
+    /// Prints a plain heading preceded by an empty line, e.g. a package group title.
+    pub fn heading(&self, msg: impl Display) {
+        let msg = msg.to_string();
+        self.write_file(Level::Info, &msg);
+        self.print(Level::Info, &format!("\n{}", msg));
+    }
+
+    /// Prints a line of output produced by a child process.
+    pub fn output(&self, line: &str) {
+        self.emit(Level::Info, "    [OUTPUT]".yellow(), line);
+    }
+
+    /// Writes a line of child process output to the run log; it is only
+    /// printed at the debug level.
+    pub fn capture(&self, line: &str) {
+        self.write_file(Level::Debug, &format!("    [OUTPUT] {}", line));
+        self.print(Level::Debug, &format!("{} {}", "    [OUTPUT]".yellow(), line));
+    }
+
+    /// Shows the current state of a long running step. On a terminal the
+    /// line is rewritten in place until [`Logger::finish_progress`] is called,
+    /// otherwise every update is printed as a detail line.
+    pub fn progress(&self, msg: impl Display) {
+        let msg = msg.to_string();
+        self.write_file(Level::Info, &format!("  -> {}", msg));
+        if !self.enabled(Level::Info) {
+            return;
+        }
+        if self.inner.stderr_only || self.enabled(Level::Debug) || !std::io::stdout().is_terminal() {
+            self.print(Level::Info, &format!("{} {}", "  ->".blue(), msg));
+            return;
+        }
+        let mut stdout = std::io::stdout();
+        let _ = write!(stdout, "\r\x1b[2K{} {}", "  ->".blue(), msg);
+        let _ = stdout.flush();
+        self.inner.progress_open.store(true, Ordering::Relaxed);
+    }
+
+    /// Ends the line rewritten by [`Logger::progress`].
+    pub fn finish_progress(&self) {
+        if self.inner.progress_open.swap(false, Ordering::Relaxed) {
+            println!();
+        }
+    }
+
+    fn emit(&self, level: Level, prefix: ColoredString, msg: impl Display) {
+        let msg = msg.to_string();
+        self.write_file(level, &format!("{} {}", prefix.clone().clear(), msg));
+        self.print(level, &format!("{} {}", prefix, msg));
+    }
+
+    fn print(&self, level: Level, line: &str) {
+        if !self.enabled(level) {
+            return;
+        }
+        if level <= Level::Warn || self.inner.stderr_only {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    fn write_file(&self, level: Level, msg: &str) {
+        let mut guard = match self.inner.file.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(file) = guard.as_mut() {
+            let _ = writeln!(
+                file,
+                "[{}] {:5} {}",
+                timestamp(SystemTime::now(), ' '),
+                level.label(),
+                strip_ansi(msg)
+            );
+        }
+    }
+}
+
+/// Default directory for per-run log files: `<state dir>/logs`.
+pub fn default_log_dir() -> Option<PathBuf> {
+    Some(crate::state::state_dir()?.join("logs"))
+}
+
+fn open_log_file(dir: &Path) -> std::io::Result<(File, PathBuf)> {
+    fs::create_dir_all(dir)?;
+    let name = format!(
+        "{}-{}.log",
+        timestamp(SystemTime::now(), '_').replace(':', ""),
+        std::process::id()
+    );
+    let path = dir.join(name);
+    let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    prune_logs(dir);
+    Ok((file, path))
+}
+
+/// Deletes all but the newest [`KEPT_LOGS`] log files; their names start
+/// with the time of the run, so they sort from oldest to newest.
+fn prune_logs(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "log"))
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(KEPT_LOGS);
+    for log in &logs[..excess] {
+        let _ = fs::remove_file(log);
+    }
+}
+
+fn strip_ansi(msg: &str) -> String {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let re = ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+    re.replace_all(msg, "").into_owned()
+}
+
+/// Formats a UTC timestamp as `YYYY-MM-DD<sep>HH:MM:SS`.
+fn timestamp(time: SystemTime, separator: char) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}{}{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        separator,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
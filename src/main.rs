@@ -1,14 +1,27 @@
 use clap::{Command, Arg};
+use std::io::IsTerminal;
 use std::process;
 use colored::*;
 
-mod restore;
+mod context;
 mod install;
+mod log;
+mod privilege;
+mod report;
+mod restore;
+mod state;
+
+use context::{Context, FastfetchOptions, FirewallOptions, PackageOptions};
+use log::{Level, Logger};
+use report::{OutputFormat, Reporter};
 
 fn main() {
+    let color_choice = color_choice_from_args();
+    configure_color(color_choice);
+
     let app = Command::new("hyde-ext")
         .arg_required_else_help(true)
-        .color(clap::ColorChoice::Always) 
+        .color(color_choice)
         .version(env!("CARGO_PKG_VERSION"))
         .author("Da4ndo <contact@da4ndo.com>")
         .about("HyDE-Ext is an extension to HyDE (HyDE_CLI) that helps automate tasks, add various custom images/wallpapers, and configurations.")
@@ -16,16 +29,165 @@ fn main() {
             .long("debug")
             .global(true) // Make debug flag available everywhere
             .action(clap::ArgAction::SetTrue)
-            .help("Sets the debug environment to true"))
+            .help("Enables debug logging (same as -v)"))
+        .arg(Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .global(true)
+            .action(clap::ArgAction::Count)
+            .help("Increases logging verbosity (-v for debug, -vv for trace)"))
+        .arg(Arg::new("log-level")
+            .long("log-level")
+            .global(true)
+            .value_name("LEVEL")
+            .value_parser(Level::NAMES)
+            .help("Sets the console log level explicitly, overriding -v and --debug"))
         .arg(Arg::new("force")
             .long("force")
             .global(true) // Make force flag available everywhere
             .action(clap::ArgAction::SetTrue)
             .help("Forces the operation to proceed with all warnings and skippings"))
+        .arg(Arg::new("color")
+            .long("color")
+            .global(true)
+            .value_name("WHEN")
+            .value_parser(["auto", "always", "never"])
+            .default_value("auto")
+            .help("Controls when to use colored output (respects NO_COLOR and CLICOLOR_FORCE in auto mode)"))
+        .arg(Arg::new("quiet")
+            .long("quiet")
+            .short('q')
+            .global(true)
+            .action(clap::ArgAction::SetTrue)
+            .help("Suppresses the banner output"))
+        .arg(Arg::new("output")
+            .long("output")
+            .short('o')
+            .global(true)
+            .value_name("FORMAT")
+            .value_parser(OutputFormat::NAMES)
+            .default_value("text")
+            .help("Output format: human readable text, a JSON document or NDJSON events streamed to stdout"))
+        .arg(Arg::new("yes")
+            .long("yes")
+            .short('y')
+            .global(true)
+            .action(clap::ArgAction::SetTrue)
+            .help("Answers yes to every confirmation prompt"))
+        .arg(Arg::new("elevate")
+            .long("elevate")
+            .global(true)
+            .value_name("TOOL")
+            .value_parser(privilege::Elevator::NAMES)
+            .help("Runs commands that need root with sudo, doas or pkexec (defaults to the first one installed)"))
+        .arg(Arg::new("assets-dir")
+            .long("assets-dir")
+            .global(true)
+            .value_name("DIR")
+            .help("Directory containing the asset folders (defaults to $HYDE_EXT_ASSETS or the installed assets)"))
         .subcommand(Command::new("install")
-             .about("Installs the specified tool or resource"))
+             .about("Installs the specified tool or resource")
+             .args(failure_policy_args())
+             .args(selection_args())
+             .arg(Arg::new("groups")
+                 .long("groups")
+                 .value_name("GROUP,...")
+                 .value_delimiter(',')
+                 .action(clap::ArgAction::Append)
+                 .help("Installs only these package groups (case-insensitive)"))
+             .arg(Arg::new("packages")
+                 .long("packages")
+                 .value_name("PACKAGE,...")
+                 .value_delimiter(',')
+                 .action(clap::ArgAction::Append)
+                 .help("Installs these packages from the package list, including optional ones"))
+             .arg(Arg::new("package-manager")
+                 .long("package-manager")
+                 .value_name("MANAGER")
+                 .value_parser(install::PackageManager::NAMES)
+                 .help("Installs packages with this backend instead of the detected AUR helper"))
+             .arg(Arg::new("firewall-rules")
+                 .long("firewall-rules")
+                 .value_name("RULE,...")
+                 .value_delimiter(',')
+                 .action(clap::ArgAction::Append)
+                 .help("Applies only these rules of UFW.toml, including ones that are not selected by default"))
+             .arg(Arg::new("fastfetch-logo")
+                 .long("fastfetch-logo")
+                 .value_name("IMAGE")
+                 .help("Uses this image (file name without extension) or 'random' as the fastfetch logo"))
+             .arg(Arg::new("fastfetch-preview")
+                 .long("fastfetch-preview")
+                 .value_name("PROTOCOL")
+                 .value_parser(install::Preview::NAMES)
+                 .help("Draws the image previews of the fastfetch logo picker with kitty graphics, sixel, half blocks or not at all")))
         .subcommand(Command::new("restore")
-             .about("Restores the application to its default state"));
+             .about("Restores the application to its default state")
+             .arg(Arg::new("backup")
+                 .long("backup")
+                 .value_name("NAME")
+                 .help("Name of the backup folder in ~/.config/cfg_backups to restore from")))
+        .subcommand(Command::new("packages")
+             .about("Works with package lists")
+             .subcommand_required(true)
+             .subcommand(Command::new("export")
+                 .about("Writes the explicitly installed packages of this system as a packages.list")
+                 .arg(Arg::new("file")
+                     .value_name("FILE")
+                     .help("File to write (prints to stdout when omitted)"))
+                 .arg(Arg::new("base")
+                     .long("base")
+                     .value_name("FILE")
+                     .action(clap::ArgAction::Append)
+                     .help("Package list to leave out (repeatable, defaults to ~/HyDE/Scripts/pkg_core.lst)"))
+                 .arg(Arg::new("rules")
+                     .long("rules")
+                     .value_name("FILE")
+                     .help("TOML file with [[rule]] group/pattern tables grouping packages by name"))
+                 .arg(Arg::new("versions")
+                     .long("versions")
+                     .action(clap::ArgAction::SetTrue)
+                     .help("Writes the installed version after every package")))
+             .subcommand(Command::new("diff")
+                 .about("Compares the installed packages with a package list")
+                 .arg(Arg::new("against")
+                     .long("against")
+                     .value_name("FILE")
+                     .help("Package list or manifest to compare with (defaults to the packages entry of the catalog)"))
+                 .arg(Arg::new("base")
+                     .long("base")
+                     .value_name("FILE")
+                     .action(clap::ArgAction::Append)
+                     .help("Package list not reported as extra (repeatable, defaults to ~/HyDE/Scripts/pkg_core.lst)"))))
+        .subcommand(Command::new("firewall")
+             .about("Works with the firewall rules of UFW.toml")
+             .subcommand_required(true)
+             .subcommand(Command::new("switch")
+                 .about("Applies a firewall profile, adding its rules and removing the other rules of UFW.toml")
+                 .arg(Arg::new("profile")
+                     .value_name("PROFILE")
+                     .required(true)
+                     .help("Name of a [[profile]] in UFW.toml (case-insensitive)"))))
+        .subcommand(Command::new("fastfetch")
+             .about("Works with the fastfetch images")
+             .subcommand_required(true)
+             .subcommand(Command::new("preview")
+                 .about("Shows the fastfetch images in the terminal")
+                 .arg(Arg::new("images")
+                     .value_name("IMAGE")
+                     .num_args(0..)
+                     .help("Images to show, file names without extension (all images when omitted)"))
+                 .arg(Arg::new("protocol")
+                     .long("protocol")
+                     .value_name("PROTOCOL")
+                     .value_parser(install::Preview::NAMES)
+                     .help("Draws the images with kitty graphics, sixel, half blocks or lists the names only (detected when omitted)"))))
+        .subcommand(Command::new("status")
+             .about("Shows which resources were installed by hyde-ext"))
+        .subcommand(Command::new("uninstall")
+             .about("Reverts resources previously installed by hyde-ext")
+             .args(failure_policy_args())
+             .args(selection_args()));
         
 
     let matches = app.clone().try_get_matches().unwrap_or_else(|e| {
@@ -40,37 +202,240 @@ fn main() {
         }
         process::exit(1);
     });
-    if matches.get_flag("debug") {
-        std::env::set_var("DEBUG", "true");
-        println!("{} Debug mode is activated.", "::".green());
+
+    let verbosity = matches.get_count("verbose").max(u8::from(matches.get_flag("debug")));
+    let level = match matches.get_one::<String>("log-level") {
+        Some(name) => Level::from_name(name).unwrap_or(Level::Info),
+        None => Level::from_verbosity(verbosity),
+    };
+    let output = matches
+        .get_one::<String>("output")
+        .and_then(|name| OutputFormat::from_name(name))
+        .unwrap_or(OutputFormat::Text);
+    let command = match matches.subcommand_name() {
+        Some("install") => "install",
+        Some("restore") => "restore",
+        Some("packages") => match matches.subcommand_matches("packages").and_then(|sub| sub.subcommand_name()) {
+            Some("diff") => "packages diff",
+            _ => "packages export",
+        },
+        Some("firewall") => "firewall switch",
+        Some("fastfetch") => "fastfetch preview",
+        Some("status") => "status",
+        Some("uninstall") => "uninstall",
+        _ => "hyde-ext",
+    };
+    let ctx = Context {
+        log: Logger::new(level, log::default_log_dir().as_deref(), output.is_machine()),
+        report: Reporter::new(output, command),
+        force: matches.get_flag("force"),
+        fail_fast: matches
+            .subcommand()
+            .is_some_and(|(_, sub)| sub.try_get_one::<bool>("fail-fast").ok().flatten() == Some(&true)),
+        assume_yes: matches.get_flag("yes"),
+        elevator: matches
+            .get_one::<String>("elevate")
+            .and_then(|name| privilege::Elevator::from_name(name))
+            .unwrap_or_else(privilege::Elevator::detect),
+        assets_dir: assets_dir(&matches),
+        packages: package_options(&matches),
+        firewall: firewall_options(&matches),
+        fastfetch: fastfetch_options(&matches),
+    };
+
+    if ctx.log.enabled(Level::Debug) {
+        ctx.log.info("Debug mode is activated.");
     }
 
-    if matches.get_flag("force") {
-        std::env::set_var("FORCE", "true");
-        println!("{} Force mode is activated.", "->".green());
+    if ctx.force {
+        ctx.log.info("Force mode is activated.");
     }
 
-    if std::env::var("DEBUG").unwrap_or_default() == "true" {
-        if cfg!(debug_assertions) {
-            println!("{} Application is running in debug build mode.", ":: Debug:".blue());
-        } else {
-            println!("{} Application is running in release build mode.", ":: Debug:".blue());
-        }
+    if cfg!(debug_assertions) {
+        ctx.log.debug("Application is running in debug build mode.");
+    } else {
+        ctx.log.debug("Application is running in release build mode.");
+    }
+    if let Some(path) = ctx.log.path() {
+        ctx.log.debug(format!("Writing run log to {}", path.display()));
     }
 
-    banner();
+    // `packages export` without a file writes the list itself to stdout.
+    let exports_to_stdout = matches
+        .subcommand_matches("packages")
+        .and_then(|sub| sub.subcommand_matches("export"))
+        .is_some_and(|export| !export.contains_id("file"));
+    if !matches.get_flag("quiet") && !output.is_machine() && !exports_to_stdout {
+        banner();
+    }
 
     match matches.subcommand() {
-        Some(("restore", _)) => {
-            restore::restore_configs();
+        Some(("restore", sub)) => {
+            restore::restore_configs(&ctx, sub.get_one::<String>("backup").map(String::as_str));
         },
-        Some(("install", _)) => {
-            install::manager::install_resources();
+        Some(("install", sub)) => {
+            install::manager::install_resources(&ctx, &selection(sub));
+        }
+        Some(("packages", sub)) => {
+            match sub.subcommand() {
+                Some(("export", export)) => install::export_packages(&ctx, &export_options(export)),
+                Some(("diff", diff)) => install::diff_packages(&ctx, &diff_options(diff)),
+                _ => {}
+            }
+        }
+        Some(("firewall", _)) => {
+            install::switch_firewall_profile(&ctx);
+        }
+        Some(("fastfetch", sub)) => {
+            let images: Vec<String> = sub
+                .subcommand_matches("preview")
+                .and_then(|preview| preview.get_many::<String>("images"))
+                .map(|images| images.cloned().collect())
+                .unwrap_or_default();
+            install::preview_images(&ctx, &images);
+        }
+        Some(("status", _)) => {
+            install::manager::status_resources(&ctx);
+        }
+        Some(("uninstall", sub)) => {
+            install::manager::uninstall_resources(&ctx, &selection(sub));
         }
         _ => {
-            println!("{} For command usage, type --help", ":: Info:".bright_blue());
+            ctx.log.info("For command usage, type --help");
         }
     }
+
+    process::exit(ctx.report.finish());
+}
+
+/// `--fail-fast` / `--keep-going` options of commands that process several entries.
+fn failure_policy_args() -> [Arg; 2] {
+    [
+        Arg::new("fail-fast")
+            .long("fail-fast")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("keep-going")
+            .help("Stops at the first entry that fails"),
+        Arg::new("keep-going")
+            .long("keep-going")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("fail-fast")
+            .help("Continues with the remaining entries when one fails (default)"),
+    ]
+}
+
+/// `--select` / `--all` / `--defaults` options to choose entries without prompting.
+fn selection_args() -> [Arg; 3] {
+    [
+        Arg::new("select")
+            .long("select")
+            .short('s')
+            .value_name("TITLE")
+            .action(clap::ArgAction::Append)
+            .conflicts_with_all(["all", "defaults"])
+            .help("Selects an entry by title without prompting (repeatable, case-insensitive, the [CATEGORY] suffix is optional)"),
+        Arg::new("all")
+            .long("all")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("defaults")
+            .help("Selects every entry without prompting"),
+        Arg::new("defaults")
+            .long("defaults")
+            .action(clap::ArgAction::SetTrue)
+            .help("Selects the entries marked as default without prompting"),
+    ]
+}
+
+fn selection(matches: &clap::ArgMatches) -> install::manager::Selection {
+    use install::manager::Selection;
+
+    if let Some(titles) = matches.get_many::<String>("select") {
+        Selection::Titles(titles.cloned().collect())
+    } else if matches.get_flag("all") {
+        Selection::All
+    } else if matches.get_flag("defaults") {
+        Selection::Defaults
+    } else {
+        Selection::Prompt
+    }
+}
+
+fn package_options(matches: &clap::ArgMatches) -> PackageOptions {
+    let values = |name: &str| -> Vec<String> {
+        matches
+            .subcommand_matches("install")
+            .and_then(|sub| sub.get_many::<String>(name))
+            .map(|values| values.map(|value| value.trim().to_string()).filter(|value| !value.is_empty()).collect())
+            .unwrap_or_default()
+    };
+    let manager = matches
+        .subcommand_matches("install")
+        .and_then(|sub| sub.get_one::<String>("package-manager"))
+        .and_then(|name| install::PackageManager::from_name(name));
+    PackageOptions { groups: values("groups"), names: values("packages"), manager }
+}
+
+fn firewall_options(matches: &clap::ArgMatches) -> FirewallOptions {
+    let rules = matches
+        .subcommand_matches("install")
+        .and_then(|sub| sub.get_many::<String>("firewall-rules"))
+        .map(|values| values.map(|value| value.trim().to_string()).filter(|value| !value.is_empty()).collect())
+        .unwrap_or_default();
+    let profile = matches
+        .subcommand_matches("firewall")
+        .and_then(|sub| sub.subcommand_matches("switch"))
+        .and_then(|switch| switch.get_one::<String>("profile"))
+        .map(|profile| profile.trim().to_string());
+    FirewallOptions { rules, profile }
+}
+
+fn fastfetch_options(matches: &clap::ArgMatches) -> FastfetchOptions {
+    let install = matches.subcommand_matches("install");
+    let preview = install
+        .and_then(|sub| sub.get_one::<String>("fastfetch-preview"))
+        .or_else(|| {
+            matches
+                .subcommand_matches("fastfetch")
+                .and_then(|sub| sub.subcommand_matches("preview"))
+                .and_then(|preview| preview.get_one::<String>("protocol"))
+        })
+        .and_then(|name| install::Preview::from_name(name));
+    FastfetchOptions {
+        logo: install.and_then(|sub| sub.get_one::<String>("fastfetch-logo")).map(|logo| logo.trim().to_string()),
+        preview,
+    }
+}
+
+fn export_options(matches: &clap::ArgMatches) -> install::ExportOptions {
+    install::ExportOptions {
+        file: matches.get_one::<String>("file").map(Into::into),
+        base: matches.get_many::<String>("base").map(|files| files.map(Into::into).collect()).unwrap_or_default(),
+        rules: matches.get_one::<String>("rules").map(Into::into),
+        versions: matches.get_flag("versions"),
+    }
+}
+
+fn diff_options(matches: &clap::ArgMatches) -> install::DiffOptions {
+    install::DiffOptions {
+        against: matches.get_one::<String>("against").map(Into::into),
+        base: matches.get_many::<String>("base").map(|files| files.map(Into::into).collect()).unwrap_or_default(),
+    }
+}
+
+/// Resolves the assets directory: `--assets-dir`, then `HYDE_EXT_ASSETS`,
+/// then the repository assets in debug builds or the installed ones.
+fn assets_dir(matches: &clap::ArgMatches) -> std::path::PathBuf {
+    if let Some(dir) = matches.get_one::<String>("assets-dir") {
+        return dir.into();
+    }
+    if let Some(dir) = std::env::var_os("HYDE_EXT_ASSETS").filter(|v| !v.is_empty()) {
+        return dir.into();
+    }
+    if cfg!(debug_assertions) {
+        "assets".into()
+    } else {
+        "/usr/share/hyde-ext/assets".into()
+    }
 }
 
 fn banner() {
@@ -96,10 +461,57 @@ $$ |  $$ |\$$$$$$$ |$$$$$$$  |$$$$$$$$\  |middle|    $$$$$$$$\ $$  /\$$\   \$$$$
     }
     println!();
     let version_info = format!("{}{}{}", "HyDE-Ext Version: ".purple(), "v".green(), env!("CARGO_PKG_VERSION").green());
-    let border_length = version_info.len() - 23; // -23 because of colors
+    let border_length = format!("HyDE-Ext Version: v{}", env!("CARGO_PKG_VERSION")).len() + 4; // +4 for the side walls and padding
     let border = "═".repeat(border_length).purple();
     println!("{}", border);
     println!("{} {} {}", "║".purple(), version_info, "║".purple());
     println!("{}", border);
     println!();
-}
\ No newline at end of file
+}
+
+/// Resolves the `--color` option before clap parses the arguments, so that
+/// help and error messages produced by clap follow the same choice.
+fn color_choice_from_args() -> clap::ColorChoice {
+    let mut args = std::env::args().skip(1);
+    let mut value = None;
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if let Some(v) = arg.strip_prefix("--color=") {
+            value = Some(v.to_string());
+        } else if arg == "--color" {
+            value = args.next();
+        }
+    }
+
+    match value.as_deref() {
+        Some("always") => clap::ColorChoice::Always,
+        Some("never") => clap::ColorChoice::Never,
+        _ => clap::ColorChoice::Auto,
+    }
+}
+
+/// Enables or disables ANSI colors for `colored` and the `dialoguer` prompts.
+///
+/// In `auto` mode `NO_COLOR` disables colors, `CLICOLOR_FORCE` forces them and
+/// otherwise colors are only used when stdout is a terminal.
+fn configure_color(choice: clap::ColorChoice) {
+    let enabled = match choice {
+        clap::ColorChoice::Always => true,
+        clap::ColorChoice::Never => false,
+        clap::ColorChoice::Auto => {
+            let env_set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty() && v != "0");
+            if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                false
+            } else if env_set("CLICOLOR_FORCE") {
+                true
+            } else {
+                std::io::stdout().is_terminal()
+            }
+        }
+    };
+
+    colored::control::set_override(enabled);
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
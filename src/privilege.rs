@@ -0,0 +1,125 @@
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::install::InstallError;
+
+/// How often the sudo timestamp is refreshed; sudo forgets it after five
+/// minutes by default.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Program running commands as root for the handlers that need it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Elevator {
+    Sudo,
+    Doas,
+    Pkexec,
+}
+
+impl Elevator {
+    /// Accepted values of `--elevate`, in order of preference for detection.
+    pub const NAMES: [&'static str; 3] = ["sudo", "doas", "pkexec"];
+
+    pub fn from_name(name: &str) -> Option<Elevator> {
+        match name {
+            "sudo" => Some(Elevator::Sudo),
+            "doas" => Some(Elevator::Doas),
+            "pkexec" => Some(Elevator::Pkexec),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Elevator::Sudo => "sudo",
+            Elevator::Doas => "doas",
+            Elevator::Pkexec => "pkexec",
+        }
+    }
+
+    /// The first of sudo, doas and pkexec found on `PATH`, sudo when none is.
+    pub fn detect() -> Elevator {
+        Elevator::NAMES
+            .into_iter()
+            .find(|name| on_path(name))
+            .and_then(Elevator::from_name)
+            .unwrap_or(Elevator::Sudo)
+    }
+
+    /// `<elevator> <program>`, to which the caller adds the arguments.
+    pub fn command(self, program: &str) -> Command {
+        let mut command = Command::new(self.name());
+        command.arg(program);
+        command
+    }
+
+    /// Asks for the password once, before anything runs, so that later
+    /// commands do not prompt in the middle of the output. doas only skips
+    /// the following prompts with `persist` in `doas.conf`; pkexec has no
+    /// cached credentials and polkit asks for every command.
+    pub fn authenticate(self) -> Result<(), InstallError> {
+        let args: &[&str] = match self {
+            Elevator::Sudo => &["-v"],
+            Elevator::Doas => &["true"],
+            Elevator::Pkexec => return Ok(()),
+        };
+        let description = format!("{} {}", self.name(), args.join(" "));
+        let status = Command::new(self.name())
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .map_err(|e| InstallError::not_started(&description, e))?;
+        if !status.success() {
+            return Err(InstallError::failed(description, status, ""));
+        }
+        Ok(())
+    }
+
+    /// Refreshes the sudo timestamp in the background until the returned
+    /// guard is dropped, so long installs do not prompt again.
+    pub fn keep_alive(self) -> Option<Keepalive> {
+        if self != Elevator::Sudo {
+            return None;
+        }
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let (stopped, condvar) = &*signal;
+            let mut stopped = stopped.lock().unwrap_or_else(|e| e.into_inner());
+            while !*stopped {
+                let (guard, timeout) =
+                    condvar.wait_timeout(stopped, REFRESH_INTERVAL).unwrap_or_else(|e| e.into_inner());
+                stopped = guard;
+                if timeout.timed_out() && !*stopped {
+                    // `-n`: never prompt from the background.
+                    let _ = Command::new("sudo").args(["-n", "-v"]).stdin(Stdio::null()).status();
+                }
+            }
+        });
+        Some(Keepalive { stop, thread: Some(thread) })
+    }
+}
+
+/// Background refresh of the sudo timestamp, stopped when dropped.
+pub struct Keepalive {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Keepalive {
+    fn drop(&mut self) {
+        let (stopped, condvar) = &*self.stop;
+        *stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        condvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Whether an executable called `name` is found on `PATH`.
+pub(crate) fn on_path(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+}
@@ -0,0 +1,269 @@
+use colored::*;
+use prettytable::{row, Table};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable, colored output (default).
+    Text,
+    /// A single JSON document printed when the command finishes.
+    Json,
+    /// One JSON event per line, printed as soon as it happens.
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 3] = ["text", "json", "ndjson"];
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "ndjson" => Some(OutputFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn is_machine(self) -> bool {
+        self != OutputFormat::Text
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Started,
+    Skipped,
+    Applied,
+    Failed,
+    Status,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    pub command: &'static str,
+    pub entry: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handler: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed: Option<bool>,
+    /// What the command produced for the entry, e.g. an exported package
+    /// list, which in text mode goes to stdout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub applied: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    command: &'static str,
+    events: &'a [Event],
+    summary: &'a Summary,
+    exit_code: i32,
+}
+
+#[derive(Serialize)]
+struct SummaryEvent<'a> {
+    event: &'static str,
+    command: &'static str,
+    #[serde(flatten)]
+    summary: &'a Summary,
+    exit_code: i32,
+}
+
+/// Collects per-entry events of a command and renders them in the selected
+/// output format. In text mode the human readable messages are printed by the
+/// logger and the events are only shown in the final summary table.
+pub struct Reporter {
+    format: OutputFormat,
+    command: &'static str,
+    events: Mutex<Vec<Event>>,
+    summary: Mutex<Summary>,
+}
+
+impl Reporter {
+    pub fn new(format: OutputFormat, command: &'static str) -> Reporter {
+        Reporter {
+            format,
+            command,
+            events: Mutex::new(Vec::new()),
+            summary: Mutex::new(Summary::default()),
+        }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn started(&self, entry: &str, handler: Option<&str>) {
+        self.push(self.event(EventKind::Started, entry, handler));
+    }
+
+    pub fn skipped(&self, entry: &str, handler: Option<&str>, reason: impl Into<String>) {
+        let mut event = self.event(EventKind::Skipped, entry, handler);
+        event.message = Some(reason.into());
+        self.push(event);
+    }
+
+    pub fn applied(&self, entry: &str, handler: Option<&str>, message: impl Into<String>) {
+        let mut event = self.event(EventKind::Applied, entry, handler);
+        event.message = Some(message.into());
+        self.push(event);
+    }
+
+    /// Like `applied`, with what the entry produced in the event.
+    pub fn applied_with_output(&self, entry: &str, handler: Option<&str>, message: impl Into<String>, output: String) {
+        let mut event = self.event(EventKind::Applied, entry, handler);
+        event.message = Some(message.into());
+        event.output = Some(output);
+        self.push(event);
+    }
+
+    pub fn failed(&self, entry: &str, handler: Option<&str>, kind: &str, message: impl Into<String>) {
+        let mut event = self.event(EventKind::Failed, entry, handler);
+        event.error_kind = Some(kind.to_string());
+        event.message = Some(message.into());
+        self.push(event);
+    }
+
+    pub fn status(&self, entry: &str, handler: Option<&str>, installed: bool, message: impl Into<String>) {
+        let mut event = self.event(EventKind::Status, entry, handler);
+        event.installed = Some(installed);
+        event.message = Some(message.into());
+        self.push(event);
+    }
+
+    /// Prints a table of succeeded, skipped and failed entries with reasons (text mode only).
+    pub fn print_table(&self) {
+        if self.format != OutputFormat::Text {
+            return;
+        }
+        let events = self.events.lock().unwrap();
+        let mut table = Table::new();
+        table.set_titles(row!["Entry", "Result", "Reason"]);
+        for event in events.iter() {
+            let result = match event.event {
+                EventKind::Applied => "succeeded".green(),
+                EventKind::Skipped => "skipped".yellow(),
+                EventKind::Failed => "failed".red(),
+                EventKind::Started | EventKind::Status => continue,
+            };
+            let reason = match (&event.error_kind, &event.message) {
+                (Some(kind), Some(message)) => format!("[{}] {}", kind, message),
+                (None, Some(message)) => message.clone(),
+                (_, None) => String::new(),
+            };
+            table.add_row(row![event.entry, result, reason]);
+        }
+
+        if !table.is_empty() {
+            let summary = self.summary.lock().unwrap();
+            println!();
+            table.printstd();
+            println!(
+                "{} {} succeeded, {} skipped, {} failed",
+                "::".blue(),
+                summary.applied,
+                summary.skipped,
+                summary.failed
+            );
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        self.summary.lock().unwrap().clone()
+    }
+
+    /// Exit code of the command: `0` when nothing failed, `2` on partial
+    /// failure and `1` when every processed entry failed.
+    pub fn exit_code(&self) -> i32 {
+        let summary = self.summary.lock().unwrap();
+        if summary.failed == 0 {
+            0
+        } else if summary.applied == 0 && summary.skipped == 0 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Prints the final summary for machine readable formats and returns the exit code.
+    pub fn finish(&self) -> i32 {
+        let exit_code = self.exit_code();
+        let summary = self.summary();
+        match self.format {
+            OutputFormat::Text => {}
+            OutputFormat::Json => {
+                let events = self.events.lock().unwrap();
+                let document = Document {
+                    command: self.command,
+                    events: &events,
+                    summary: &summary,
+                    exit_code,
+                };
+                emit(&serde_json::to_string_pretty(&document).unwrap());
+            }
+            OutputFormat::Ndjson => {
+                let event = SummaryEvent {
+                    event: "summary",
+                    command: self.command,
+                    summary: &summary,
+                    exit_code,
+                };
+                emit(&serde_json::to_string(&event).unwrap());
+            }
+        }
+        exit_code
+    }
+
+    fn event(&self, event: EventKind, entry: &str, handler: Option<&str>) -> Event {
+        Event {
+            event,
+            command: self.command,
+            entry: entry.to_string(),
+            handler: handler.map(str::to_string),
+            message: None,
+            error_kind: None,
+            installed: None,
+            output: None,
+        }
+    }
+
+    fn push(&self, event: Event) {
+        {
+            let mut summary = self.summary.lock().unwrap();
+            match event.event {
+                EventKind::Applied => summary.applied += 1,
+                EventKind::Skipped => summary.skipped += 1,
+                EventKind::Failed => summary.failed += 1,
+                EventKind::Started | EventKind::Status => {}
+            }
+        }
+
+        if self.format == OutputFormat::Ndjson {
+            emit(&serde_json::to_string(&event).unwrap());
+        }
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// Writes a line to stdout, ignoring errors such as a closed pipe so that
+/// consumers may stop reading early.
+fn emit(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
@@ -1,23 +1,36 @@
 use colored::*;
 use dialoguer::{theme::ColorfulTheme, Confirm, Select};
 use std::fs;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-pub fn restore_configs() {
-    match select_backup_folder() {
+use crate::context::Context;
+
+/// Result of restoring a single backed up file.
+enum Restored {
+    Appended,
+    NothingToRestore,
+    Declined,
+}
+
+pub fn restore_configs(ctx: &Context, backup: Option<&str>) {
+    match select_backup_folder(backup) {
         Ok(folder_path) => {
-            println!("{} {}", "  -> Selected: ".yellow(), folder_path.display());
-            if let Err(e) = process_backup_folder(&folder_path) {
-                eprintln!("{} {}", ":: Error:".red(), e);
+            ctx.log.detail(format!("Selected: {}", folder_path.display()));
+            if let Err(e) = process_backup_folder(ctx, &folder_path) {
+                ctx.log.error(&e);
+                ctx.report.failed(&folder_path.display().to_string(), None, "io", e.to_string());
             }
         }
-        Err(e) => eprintln!("{} {}", ":: Error:".red(), e),
+        Err(e) => {
+            ctx.log.error(&e);
+            ctx.report.failed("backup selection", None, "selection", e.to_string());
+        }
     }
 }
 
-fn select_backup_folder() -> io::Result<PathBuf> {
+fn select_backup_folder(backup: Option<&str>) -> io::Result<PathBuf> {
     let home_dir = std::env::var("HOME").unwrap_or_default();
     let backup_root = Path::new(&home_dir).join(".config/cfg_backups");
     let mut folders: Vec<_> = WalkDir::new(backup_root)
@@ -35,6 +48,14 @@ fn select_backup_folder() -> io::Result<PathBuf> {
         .map(|dir| dir.file_name().to_string_lossy().into_owned())
         .collect();
 
+    if let Some(name) = backup {
+        return folders
+            .iter()
+            .find(|dir| dir.file_name().to_string_lossy() == name)
+            .map(|dir| dir.path().to_path_buf())
+            .ok_or_else(|| io::Error::other(format!("Backup folder '{}' was not found", name)));
+    }
+
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt(
             " Choose a backup folder to restore from"
@@ -44,13 +65,13 @@ fn select_backup_folder() -> io::Result<PathBuf> {
         .default(0)
         .items(&folder_names)
         .interact_opt()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-        .ok_or(io::Error::new(io::ErrorKind::Other, "No selection made"))?;
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .ok_or(io::Error::other("No selection made"))?;
 
     Ok(folders[selection].path().to_path_buf())
 }
 
-fn process_backup_folder(backup_folder: &Path) -> io::Result<()> {
+fn process_backup_folder(ctx: &Context, backup_folder: &Path) -> io::Result<()> {
     let home_dir = std::env::var("HOME").unwrap_or_default();
     let config_root = Path::new(&home_dir).join(".config");
     let skip_extensions = ["png", "jpg", "svg"]; // Define extensions to skip
@@ -73,7 +94,7 @@ fn process_backup_folder(backup_folder: &Path) -> io::Result<()> {
         let relative_path = entry
             .path()
             .strip_prefix(backup_folder)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+            .map_err(|e| io::Error::other(e.to_string()))?
             .to_path_buf();
         let target_path = if relative_path.starts_with(".config") {
             config_root.join(relative_path.strip_prefix(".config").unwrap())
@@ -82,49 +103,44 @@ fn process_backup_folder(backup_folder: &Path) -> io::Result<()> {
         };
 
         if !target_path.exists() {
-            if std::env::var("DEBUG").unwrap_or_default() == "true" {
-                println!(
-                    "{} Skipped: {} exists in backup but not in the production configuration.",
-                    ":: Debug:".blue(),
-                    target_path.display()
-                );
-            }
+            ctx.log.debug(format!(
+                "Skipped: {} exists in backup but not in the production configuration.",
+                target_path.display()
+            ));
             continue;
         }
 
-        match append_custom_configs(entry.path(), &target_path) {
-            Ok(append_success) => {
-                if append_success {
-                    count += 1;
-                    println!(
-                        "{} Successfully restored custom configurations for {}",
-                        "    ->".green(),
-                        entry.path().display()
-                    );
-                }
+        let entry_name = target_path.display().to_string();
+        match append_custom_configs(ctx, entry.path(), &target_path) {
+            Ok(Restored::Appended) => {
+                count += 1;
+                ctx.log.success(format!("restored custom configurations for {}", entry.path().display()));
+                ctx.report.applied(&entry_name, None, format!("restored from {}", entry.path().display()));
+            }
+            Ok(Restored::Declined) => {
+                ctx.report.skipped(&entry_name, None, "Target already contains customized configurations.");
+            }
+            Ok(Restored::NothingToRestore) => {}
+            Err(e) => {
+                ctx.log.error(format!("Failed to process {}: {}", entry.path().display(), e));
+                ctx.report.failed(&entry_name, None, "io", e.to_string());
             }
-            Err(e) => eprintln!(
-                "{} Failed to process {}: {}",
-                ":: Error:".red(),
-                entry.path().display(),
-                e
-            ),
         }
     }
 
-    println!(
-        "\n{} {}\n",
-        "Process completed. Total files restored:".yellow(),
-        count
-    );
+    if !ctx.report.format().is_machine() {
+        println!(
+            "\n{} {}\n",
+            "Process completed. Total files restored:".yellow(),
+            count
+        );
+    }
 
     Ok(())
 }
 
-fn append_custom_configs(source_path: &Path, target_path: &Path) -> Result<bool, io::Error> {
-    if std::env::var("DEBUG").unwrap_or_default() == "true" {
-        println!("{} Processing {}", "    ->".blue(), source_path.display());
-    }
+fn append_custom_configs(ctx: &Context, source_path: &Path, target_path: &Path) -> Result<Restored, io::Error> {
+    ctx.log.trace(format!("Processing {}", source_path.display()));
 
     let specific_content = format!(
         "{}\n{}\n{}",
@@ -163,49 +179,33 @@ fn append_custom_configs(source_path: &Path, target_path: &Path) -> Result<bool,
     }
 
     if append {
-        if std::env::var("DEBUG").unwrap_or_default() == "true" {
-            println!(
-                "{} Processing source: {}",
-                "    ->".blue(),
-                source_path.display()
-            );
-            println!(
-                "{} Targeting path: {}",
-                "    ->".blue(),
-                target_path.display()
-            );
-        }
-        println!("{} {}", "    -> Found:".green(), source_path.display());
+        ctx.log.debug(format!("Processing source: {}", source_path.display()));
+        ctx.log.debug(format!("Targeting path: {}", target_path.display()));
+        ctx.log.detail(format!("Found: {}", source_path.display()));
 
         let target_file_content = fs::read_to_string(target_path)?;
         if target_file_content.contains(
             "# ================== Customized Configurations Below ===========================",
         ) {
-            let proceed = Confirm::with_theme(&ColorfulTheme::default())
+            // Without a terminal to ask on, keep the prompt's default answer (no)
+            let proceed = ctx.assume_yes || (io::stdin().is_terminal() && Confirm::with_theme(&ColorfulTheme::default())
                 .with_prompt(format!("{} The file '{}' already contains customized configurations. Do you want to continue restoring?", ":: Warning:".yellow(), target_path.file_name().unwrap_or_default().to_string_lossy()))
                 .default(false)
                 .interact()
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                .map_err(|e| io::Error::other(e.to_string()))?);
 
             if !proceed {
-                println!(
-                    "{} file: {}",
-                    "    -> Skipping".blue(),
-                    target_path.display()
-                );
-                return Ok(false);
+                ctx.log.skip(format!("file: {}", target_path.display()));
+                return Ok(Restored::Declined);
             }
         }
     }
 
     if !content_to_append.is_empty() {
-        if std::env::var("DEBUG").unwrap_or_default() == "true" {
-            println!(
-                "{} Opening file for restored custom configurations: {}",
-                "    ::".blue(),
-                target_path.display()
-            );
-        }
+        ctx.log.debug(format!(
+            "Opening file for restored custom configurations: {}",
+            target_path.display()
+        ));
 
         let mut target_file = fs::OpenOptions::new().append(true).open(target_path)?;
 
@@ -224,8 +224,8 @@ fn append_custom_configs(source_path: &Path, target_path: &Path) -> Result<bool,
 
         writeln!(target_file, "{}", content_to_append)?;
 
-        return Ok(true);
+        return Ok(Restored::Appended);
     }
 
-    Ok(false)
+    Ok(Restored::NothingToRestore)
 }
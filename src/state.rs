@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Record of everything hyde-ext applied, used by `status` and `uninstall`.
+///
+/// Stored as `state.toml` in the state directory.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<Record>,
+}
+
+/// What an install handler changed for a single catalog entry.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Record {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub handler: String,
+    #[serde(default)]
+    pub installed_at: u64,
+    /// File that content was appended to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_path: Option<String>,
+    /// Exact content appended to `target_path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appended: Option<String>,
+    /// Lines of `target_path` that were commented out, as they were before.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commented: Vec<String>,
+    /// Files created by the handler.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// Packages that were not installed before hyde-ext installed them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    /// Services enabled by hyde-ext, user services prefixed with `user:`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    /// Groups hyde-ext added the user to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_groups: Vec<String>,
+    /// Firewall rules added by hyde-ext, as written after `ufw`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub firewall_rules: Vec<String>,
+    /// Checksum of each copied file as it was written, to tell whether it
+    /// was changed since.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+    /// Top-level keys of `target_path` replaced by the handler, with the JSON
+    /// each had before, `null` when it was absent; restored on uninstall.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub replaced: BTreeMap<String, String>,
+}
+
+/// `$XDG_STATE_HOME/hyde-ext`, falling back to `~/.local/state/hyde-ext`.
+pub fn state_dir() -> Option<PathBuf> {
+    let state_home = match std::env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_home.join("hyde-ext"))
+}
+
+fn state_file() -> io::Result<PathBuf> {
+    state_dir()
+        .map(|dir| dir.join("state.toml"))
+        .ok_or_else(|| io::Error::other("Unable to determine the state directory"))
+}
+
+impl State {
+    pub fn load() -> io::Result<State> {
+        let path = state_file()?;
+        if !path.exists() {
+            return Ok(State::default());
+        }
+        let contents = fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|e| io::Error::other(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = state_file()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    pub fn get(&self, title: &str) -> Option<&Record> {
+        self.entries.iter().find(|record| record.title == title)
+    }
+
+    /// Inserts or replaces the record with the same title.
+    pub fn upsert(&mut self, record: Record) {
+        match self.entries.iter_mut().find(|r| r.title == record.title) {
+            Some(existing) => *existing = record,
+            None => self.entries.push(record),
+        }
+    }
+
+    pub fn remove(&mut self, title: &str) {
+        self.entries.retain(|record| record.title != title);
+    }
+}
@@ -0,0 +1,264 @@
+//! Test harness running the `hyde-ext` binary against a sandboxed `HOME`, a
+//! copy of the fixture assets and stub system commands that record their
+//! arguments instead of touching the machine.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+/// Commands replaced by recording stubs in every sandbox.
+pub const STUBBED_COMMANDS: [&str; 18] = [
+    "yay", "sudo", "doas", "pkexec", "ufw", "pacman", "bluetoothctl", "hyprctl", "lspci", "flatpak", "cargo",
+    "systemctl", "usermod", "gpasswd", "id", "firewall-cmd", "firewall-offline-cmd", "nft",
+];
+
+/// Package managers whose default stub behaves like a package database: `-Si`
+/// finds every package, in the `extra` repository with pacman and in the
+/// `aur` with the helpers, `-S --needed` installs, `-Rns` removes and `-Qq`
+/// lists the packages in `installed.txt`.
+pub const PACKAGE_MANAGERS: [&str; 4] = ["pacman", "yay", "paru", "pikaur"];
+
+const PACKAGE_DATABASE: &str = r#"db='{installed}'
+repository=aur
+[[ "$(basename "$0")" == pacman ]] && repository=extra
+case "$1" in
+  -Si)
+    shift
+    for package in "$@"; do
+      printf 'Repository      : %s\nName            : %s\nVersion         : 1.0-1\nConflicts With  : None\n\n' \
+        "$repository" "$package"
+    done ;;
+  -Qq) cat "$db" ;;
+  -Rns)
+    shift
+    for package in "$@"; do sed -i "/^$package\$/d" "$db"; done ;;
+  *)
+    args=("$@")
+    for i in "${!args[@]}"; do
+      if [[ "${args[$i]}" == --needed ]]; then
+        for package in "${args[@]:$((i + 1))}"; do
+          [[ "$package" == -* ]] && continue
+          grep -qx "$package" "$db" || echo "$package" >> "$db"
+        done
+      fi
+    done ;;
+esac"#;
+
+/// `flatpak` and `cargo` stubs keeping their packages in the same database,
+/// as `flatpak:<id>` and `cargo:<crate>` lines.
+const FLATPAK: &str = r#"db='{installed}'
+case "$1" in
+  list) sed -n 's/^flatpak://p' "$db" ;;
+  install) for id in "${@:5}"; do echo "flatpak:$id" >> "$db"; done ;;
+  uninstall) for id in "${@:4}"; do sed -i "/^flatpak:$id\$/d" "$db"; done ;;
+esac"#;
+
+const CARGO: &str = r#"db='{installed}'
+case "$1 $2" in
+  "install --list") sed -n 's/^cargo:\(.*\)/\1 v0.1.0:\n    \1/p' "$db" ;;
+  install*) for name in "${@:2}"; do echo "cargo:$name" >> "$db"; done ;;
+  uninstall*) for name in "${@:2}"; do sed -i "/^cargo:$name\$/d" "$db"; done ;;
+esac"#;
+
+pub struct Sandbox {
+    _dir: TempDir,
+    pub root: PathBuf,
+    pub home: PathBuf,
+    pub assets: PathBuf,
+    pub bin: PathBuf,
+    calls: PathBuf,
+    installed: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new() -> Sandbox {
+        let dir = tempfile::tempdir().expect("failed to create sandbox");
+        let root = dir.path().to_path_buf();
+        let sandbox = Sandbox {
+            home: root.join("home"),
+            assets: root.join("assets"),
+            bin: root.join("bin"),
+            calls: root.join("calls.log"),
+            installed: root.join("installed.txt"),
+            root,
+            _dir: dir,
+        };
+
+        fs::create_dir_all(&sandbox.home).unwrap();
+        fs::create_dir_all(&sandbox.bin).unwrap();
+        fs::write(&sandbox.calls, "").unwrap();
+        fs::write(&sandbox.installed, "").unwrap();
+        copy_dir(&fixtures_dir().join("assets"), &sandbox.assets);
+
+        for name in STUBBED_COMMANDS {
+            sandbox.stub(name, "");
+        }
+        for name in PACKAGE_MANAGERS.iter().filter(|name| STUBBED_COMMANDS.contains(name)) {
+            sandbox.stub_package_manager(name, "");
+        }
+        let installed = sandbox.installed.display().to_string();
+        sandbox.stub("flatpak", &FLATPAK.replace("{installed}", &installed));
+        sandbox.stub("cargo", &CARGO.replace("{installed}", &installed));
+        // No service is enabled and the user `tester` is only in `wheel`.
+        sandbox.stub("systemctl", r#"[[ "$*" == *is-enabled* ]] && exit 1; exit 0"#);
+        sandbox.stub("id", r#"[[ "$1" == -un ]] && echo tester; [[ "$1" == -nG ]] && echo "tester wheel"; exit 0"#);
+        // `sudo`, `doas` and `pkexec` run the wrapped command so that its stub
+        // records the call as well; `sudo -v` and `doas true` authenticate.
+        sandbox.stub("sudo", r#"[[ "$1" == -v || "$1" == -n ]] && exit 0; exec "$@""#);
+        sandbox.stub("doas", r#"exec "$@""#);
+        sandbox.stub("pkexec", r#"exec "$@""#);
+        sandbox
+    }
+
+    /// Installs a stub for `name` on `PATH` that records its arguments and then
+    /// runs `body` as a bash script (exit status 0 when `body` is empty).
+    pub fn stub(&self, name: &str, body: &str) {
+        let path = self.bin.join(name);
+        let script = format!(
+            "#!/bin/bash\nprintf '%s\\n' \"{name} $*\" >> '{calls}'\n{body}\n",
+            name = name,
+            calls = self.calls.display(),
+            body = body
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// Installs a package manager stub that runs `body` first and then acts on
+    /// the sandbox package database (see [`PACKAGE_MANAGERS`]).
+    pub fn stub_package_manager(&self, name: &str, body: &str) {
+        let database = PACKAGE_DATABASE.replace("{installed}", &self.installed.display().to_string());
+        self.stub(name, &format!("{}\n{}", body, database));
+    }
+
+    /// Packages currently installed in the sandbox package database.
+    pub fn installed_packages(&self) -> Vec<String> {
+        fs::read_to_string(&self.installed).unwrap().lines().map(str::to_string).collect()
+    }
+
+    /// Marks packages as installed before hyde-ext runs.
+    pub fn set_installed_packages(&self, packages: &[&str]) {
+        let contents: String = packages.iter().map(|package| format!("{}\n", package)).collect();
+        fs::write(&self.installed, contents).unwrap();
+    }
+
+    /// Recorded `-S --needed` install transactions of a stubbed package manager.
+    pub fn installs(&self, name: &str) -> Vec<String> {
+        self.calls_to(name).into_iter().filter(|call| call.contains("-S --needed")).collect()
+    }
+
+    /// Every recorded stub invocation as `"<command> <args>"`, in order.
+    pub fn calls(&self) -> Vec<String> {
+        fs::read_to_string(&self.calls)
+            .unwrap()
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    }
+
+    /// Recorded invocations of a single stubbed command.
+    pub fn calls_to(&self, name: &str) -> Vec<String> {
+        let prefix = format!("{} ", name);
+        self.calls()
+            .into_iter()
+            .filter(|call| call.starts_with(&prefix) || call == name)
+            .collect()
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let path = format!("{}:{}", self.bin.display(), std::env::var("PATH").unwrap_or_default());
+        let mut command = Command::new(env!("CARGO_BIN_EXE_hyde-ext"));
+        command
+            .args(args)
+            .current_dir(&self.root)
+            .env_clear()
+            .env("HOME", &self.home)
+            .env("PATH", path)
+            .env("HYDE_EXT_ASSETS", &self.assets)
+            .env("NO_COLOR", "1")
+            .env("TERM", "dumb");
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("failed to run hyde-ext")
+    }
+
+    /// Runs hyde-ext and fails the test with its output when it does not exit successfully.
+    pub fn run_ok(&self, args: &[&str]) -> Output {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "hyde-ext {:?} failed with {}\nstdout:\n{}\nstderr:\n{}",
+            args,
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    pub fn home_path(&self, relative: &str) -> PathBuf {
+        self.home.join(relative)
+    }
+
+    pub fn write_home(&self, relative: &str, contents: &str) -> PathBuf {
+        let path = self.home_path(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn read_home(&self, relative: &str) -> String {
+        fs::read_to_string(self.home_path(relative)).unwrap()
+    }
+
+    pub fn write_asset(&self, relative: &str, contents: &str) -> PathBuf {
+        let path = self.assets.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn read_asset(&self, relative: &str) -> String {
+        fs::read_to_string(self.assets.join(relative)).unwrap()
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Parses NDJSON output into one JSON value per line.
+pub fn events(output: &Output) -> Vec<serde_json::Value> {
+    stdout(output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("invalid event {:?}: {}", line, e)))
+        .collect()
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn copy_dir(source: &Path, target: &Path) {
+    fs::create_dir_all(target).unwrap();
+    for entry in fs::read_dir(source).unwrap() {
+        let entry = entry.unwrap();
+        let target_path = target.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target_path);
+        } else {
+            fs::copy(entry.path(), target_path).unwrap();
+        }
+    }
+}
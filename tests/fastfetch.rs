@@ -0,0 +1,279 @@
+mod common;
+
+use common::{events, stdout, Sandbox};
+use serde_json::{json, Value};
+
+const CONFIG: &str = ".config/fastfetch/config.jsonc";
+const LOGOS: &str = ".config/fastfetch/logos";
+
+fn read_config(sandbox: &Sandbox) -> Value {
+    serde_json::from_str(&sandbox.read_home(CONFIG)).unwrap()
+}
+
+/// A sandbox whose shipped `one.png` and `two.png` are decodable images.
+fn with_images() -> Sandbox {
+    let sandbox = Sandbox::new();
+    write_png(&sandbox, "one.png", [255, 0, 0, 255]);
+    write_png(&sandbox, "two.png", [0, 0, 255, 255]);
+    sandbox
+}
+
+#[test]
+fn catalog_logo_is_written_to_a_new_config() {
+    let sandbox = with_images();
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo"]);
+
+    let image = sandbox.home_path(&format!("{}/two.png", LOGOS));
+    // 28x14 cells of 10x20 pixels.
+    assert_eq!(image::image_dimensions(&image).unwrap(), (280, 280));
+    assert!(!sandbox.home_path(&format!("{}/one.png", LOGOS)).exists());
+    let config = read_config(&sandbox);
+    assert_eq!(
+        config["logo"],
+        json!({
+            "type": "kitty-direct",
+            "source": image.display().to_string(),
+            "width": 28,
+            "height": 14,
+            "padding": { "top": 1, "left": 2 },
+        })
+    );
+    assert_eq!(config["modules"], json!(["title", "os", "colors"]));
+    assert!(config["$schema"].is_string());
+}
+
+#[test]
+fn random_logo_picks_an_installed_image_per_shell_start() {
+    let sandbox = with_images();
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo", "--fastfetch-logo", "Random"]);
+
+    let logos = sandbox.home_path(LOGOS);
+    assert_eq!(
+        read_config(&sandbox)["logo"]["source"],
+        format!("$(find \"{}\" -maxdepth 1 -name \"*.png\" | shuf -n 1)", logos.display())
+    );
+    assert!(logos.join("one.png").exists());
+    assert!(logos.join("two.png").exists());
+}
+
+#[test]
+fn existing_config_keeps_its_other_settings() {
+    let sandbox = with_images();
+    sandbox.write_home(
+        CONFIG,
+        r#"// My fastfetch setup
+{
+  "logo": {
+    "source": "arch", /* replaced */
+    "color": { "1": "blue" },
+  },
+  "display": { "separator": " -> " },
+  "modules": ["os", "host",],
+}
+"#,
+    );
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo", "--fastfetch-logo", "one"]);
+
+    // Only the replaced keys are rewritten; comments and the rest of the
+    // formatting, trailing comma included, are kept.
+    let content = sandbox.read_home(CONFIG);
+    assert!(content.starts_with("// My fastfetch setup\n{\n  \"logo\": {\n"));
+    assert!(!content.contains("/* replaced */"));
+    assert!(content.contains("\n  \"display\": { \"separator\": \" -> \" },\n"));
+    assert!(content.ends_with("\n  ],\n}\n"));
+    let json = content.replacen("// My fastfetch setup", "", 1).replace("],\n}", "]\n}");
+    let config: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(config["logo"]["source"], sandbox.home_path(&format!("{}/one.png", LOGOS)).display().to_string());
+    assert_eq!(config["logo"]["color"], json!({ "1": "blue" }));
+    assert_eq!(config["display"], json!({ "separator": " -> " }));
+    assert_eq!(config["modules"], json!(["title", "os", "colors"]));
+}
+
+#[test]
+fn unchanged_config_is_skipped() {
+    let sandbox = with_images();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo"]);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "fastfetch logo"]);
+
+    assert!(events(&output).iter().any(|event| event["event"] == "skipped"));
+}
+
+#[test]
+fn jpeg_images_are_cropped_and_converted_to_png() {
+    let sandbox = with_images();
+    // Red, green and blue thirds; cropping to a square keeps the green middle.
+    let wide = image::RgbImage::from_fn(60, 20, |x, _| match x / 20 {
+        0 => image::Rgb([255, 0, 0]),
+        1 => image::Rgb([0, 255, 0]),
+        _ => image::Rgb([0, 0, 255]),
+    });
+    wide.save(sandbox.assets.join("FastFetchAssets/pngs/wide.jpg")).unwrap();
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo", "--fastfetch-logo", "wide"]);
+
+    let logo = image::open(sandbox.home_path(&format!("{}/wide.png", LOGOS))).unwrap().to_rgb8();
+    assert_eq!(logo.dimensions(), (280, 280));
+    for x in [0, 140, 279] {
+        let [r, g, b] = logo.get_pixel(x, 140).0;
+        assert!(g > 200 && r < 60 && b < 60, "pixel {} is {:?}", x, [r, g, b]);
+    }
+    assert!(!sandbox.home_path(&format!("{}/wide.jpg", LOGOS)).exists());
+    assert!(read_config(&sandbox)["logo"]["source"].as_str().unwrap().ends_with("/wide.png"));
+}
+
+#[test]
+fn text_logos_use_the_ascii_rendition() {
+    let sandbox = with_images();
+    let settings = sandbox.read_asset("FastFetchAssets/FastFetchAssets.toml");
+    let settings = settings.replace("type = \"kitty-direct\"", "type = \"file\"\nart = \"ascii\"");
+    sandbox.write_asset("FastFetchAssets/FastFetchAssets.toml", &settings);
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo", "--fastfetch-logo", "one"]);
+
+    let text = sandbox.home_path(&format!("{}/one.txt", LOGOS));
+    assert_eq!(read_config(&sandbox)["logo"]["source"], text.display().to_string());
+    // Pure red is dark enough for the fourth character of the ramp.
+    assert_eq!(sandbox.read_home(&format!("{}/one.txt", LOGOS)), format!("{}\n", "-".repeat(28)).repeat(14));
+    assert!(sandbox.home_path(&format!("{}/one.png", LOGOS)).exists());
+}
+
+#[test]
+fn normalized_logos_are_reused_from_the_cache() {
+    let sandbox = with_images();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo"]);
+    let cache = sandbox.home_path(".cache/hyde-ext/fastfetch");
+    let cached: Vec<_> = std::fs::read_dir(&cache).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(cached.len(), 1);
+
+    // A changed cache entry shows that the image is not decoded again.
+    std::fs::write(&cached[0], "cached logo").unwrap();
+    std::fs::remove_file(sandbox.home_path(&format!("{}/two.png", LOGOS))).unwrap();
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "fastfetch logo"]);
+
+    assert!(events(&output).iter().any(|event| event["event"] == "applied"));
+    assert_eq!(sandbox.read_home(&format!("{}/two.png", LOGOS)), "cached logo");
+}
+
+#[test]
+fn undecodable_images_fail_to_parse() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "fastfetch logo", "--fastfetch-logo", "one"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "parse");
+    assert!(failed["message"].as_str().unwrap().contains("one.png is not a supported image"));
+    assert!(!sandbox.home_path(CONFIG).exists());
+}
+
+#[test]
+fn unknown_logo_lists_the_images() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "fastfetch logo", "--fastfetch-logo", "three"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+    assert!(failed["message"].as_str().unwrap().contains("Available images: random, one, two"));
+    assert!(!sandbox.home_path(CONFIG).exists());
+}
+
+#[test]
+fn invalid_config_is_only_replaced_with_force() {
+    let sandbox = with_images();
+    sandbox.write_home(CONFIG, "{ not json");
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "fastfetch logo"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "parse");
+    assert_eq!(sandbox.read_home(CONFIG), "{ not json");
+
+    sandbox.run_ok(&["-q", "--force", "install", "--select", "fastfetch logo"]);
+
+    assert_eq!(read_config(&sandbox)["logo"]["type"], "kitty-direct");
+}
+
+#[test]
+fn uninstall_removes_the_created_config() {
+    let sandbox = with_images();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo"]);
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "fastfetch logo"]);
+
+    assert!(!sandbox.home_path(CONFIG).exists());
+    assert!(!sandbox.home_path(&format!("{}/two.png", LOGOS)).exists());
+}
+
+#[test]
+fn uninstall_restores_the_replaced_keys_of_an_existing_config() {
+    let sandbox = with_images();
+    let existing = "{\n  // Shown on the left\n  \"logo\": \"arch\",\n  \"display\": { \"separator\": \" -> \" }\n}\n";
+    sandbox.write_home(CONFIG, existing);
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo"]);
+    // A second install keeps the values from before the first one.
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch logo", "--fastfetch-logo", "one"]);
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "fastfetch logo"]);
+
+    assert_eq!(sandbox.read_home(CONFIG), existing);
+    assert!(!sandbox.home_path(&format!("{}/one.png", LOGOS)).exists());
+    assert!(!sandbox.home_path(&format!("{}/two.png", LOGOS)).exists());
+}
+
+/// Writes a 4x4 PNG of one color to the shipped images.
+fn write_png(sandbox: &Sandbox, name: &str, color: [u8; 4]) {
+    let path = sandbox.assets.join("FastFetchAssets/pngs").join(name);
+    image::RgbaImage::from_pixel(4, 4, image::Rgba(color)).save(path).unwrap();
+}
+
+#[test]
+fn half_block_previews_show_the_image_colors() {
+    let sandbox = Sandbox::new();
+    write_png(&sandbox, "red.png", [255, 0, 0, 255]);
+
+    let output = sandbox.run_ok(&["-q", "fastfetch", "preview", "RED", "--protocol", "blocks"]);
+
+    let lines: Vec<String> = stdout(&output).lines().map(str::to_string).collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[0].contains("\x1b[38;2;255;0;0;48;2;255;0;0m▀\x1b[0m"));
+    assert_eq!(lines[8], "1. red");
+}
+
+#[test]
+fn kitty_and_sixel_previews_use_graphics_escapes() {
+    let sandbox = Sandbox::new();
+    write_png(&sandbox, "red.png", [255, 0, 0, 255]);
+
+    let kitty = stdout(&sandbox.run_ok(&["-q", "fastfetch", "preview", "red", "--protocol", "kitty"]));
+    let sixel = stdout(&sandbox.run_ok(&["-q", "fastfetch", "preview", "red", "--protocol", "sixel"]));
+
+    assert!(kitty.contains("\x1b_Ga=T,f=100,q=2,C=1,c=16,r=8,m=0;iVBORw0KGgo"));
+    assert!(sixel.contains("\x1bP0;1q\"1;1;128;128"));
+    // Pure red is color 180 of the 6x6x6 palette, drawn in runs of full columns.
+    assert!(sixel.contains("#180!128~"));
+}
+
+#[test]
+fn previews_without_graphics_list_every_image() {
+    let sandbox = Sandbox::new();
+    write_png(&sandbox, "zero.png", [0, 0, 0, 0]);
+
+    let output = sandbox.run_ok(&["-q", "fastfetch", "preview", "--protocol", "none"]);
+
+    assert_eq!(stdout(&output), format!("{:18}{:18}3. zero\n", "1. one", "2. two"));
+}
+
+#[test]
+fn previewing_an_unknown_image_fails() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "fastfetch", "preview", "three"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+}
@@ -0,0 +1,390 @@
+mod common;
+
+use common::{events, fixtures_dir, stderr, Sandbox};
+
+/// `ufw` calls that change rules, without the queries, policy and logging calls.
+fn rule_calls(sandbox: &Sandbox) -> Vec<String> {
+    let other = ["ufw default", "ufw logging", "ufw status", "ufw show", "ufw --force enable"];
+    sandbox
+        .calls_to("ufw")
+        .into_iter()
+        .filter(|call| !other.iter().any(|prefix| call.starts_with(prefix)))
+        .collect()
+}
+
+/// Makes `ufw status verbose` and `ufw show added` report the configured
+/// firewall of `tests/fixtures/ufw`.
+fn stub_configured_ufw(sandbox: &Sandbox) {
+    sandbox.stub("ufw", &configured_ufw());
+}
+
+/// Body of the `ufw` stub reporting the configured firewall.
+fn configured_ufw() -> String {
+    let fixtures = fixtures_dir().join("ufw");
+    format!(
+        r#"case "$1 $2" in
+  "status verbose") cat '{}' ;;
+  "show added") cat '{}' ;;
+esac"#,
+        fixtures.join("status-verbose.txt").display(),
+        fixtures.join("show-added.txt").display()
+    )
+}
+
+#[test]
+fn default_rules_are_applied_without_prompting() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration"]);
+
+    assert_eq!(rule_calls(&sandbox), vec!["ufw allow 443/tcp", "ufw allow ssh"]);
+}
+
+#[test]
+fn named_rules_use_the_full_syntax() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "Postgres"]);
+
+    assert_eq!(
+        rule_calls(&sandbox),
+        vec!["ufw allow in on wg0 proto tcp from 10.0.0.0/8 to any port 5432 comment Team database"]
+    );
+}
+
+#[test]
+fn unknown_rule_fails_the_entry() {
+    let sandbox = Sandbox::new();
+
+    let output =
+        sandbox.run(&["-o", "ndjson", "install", "--select", "ufw configuration", "--firewall-rules", "mysql"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+    assert!(sandbox.calls_to("ufw").is_empty());
+}
+
+#[test]
+fn invalid_rule_is_reported() {
+    let sandbox = Sandbox::new();
+    let catalog = sandbox.read_asset("UFW/UFW.toml");
+    let invalid = "[[rule]]\nname = \"web\"\nport = 80\napp = \"Nginx Full\"\n";
+    sandbox.write_asset("UFW/UFW.toml", &format!("{}\n{}", catalog, invalid));
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "ufw configuration"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "parse");
+    assert!(failed["message"].as_str().unwrap().contains("rule 'web' sets both port and app"));
+}
+
+#[test]
+fn configured_firewall_is_left_alone() {
+    let sandbox = Sandbox::new();
+    stub_configured_ufw(&sandbox);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "ufw configuration"]);
+
+    let calls = sandbox.calls_to("ufw");
+    assert_eq!(calls, vec!["ufw status verbose", "ufw show added"]);
+    let skipped = events(&output).into_iter().find(|event| event["event"] == "skipped").unwrap();
+    assert_eq!(skipped["message"], "The firewall is already configured, 2 rule(s) in place.");
+    assert!(stderr(&output)
+        .contains("Rules not declared in UFW.toml are left in place: allow from 192.168.1.0/24 to any port 8080"));
+}
+
+#[test]
+fn deselected_rules_added_by_hyde_ext_are_removed() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https,postgres"]);
+    stub_configured_ufw(&sandbox);
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https"]);
+
+    assert_eq!(
+        rule_calls(&sandbox),
+        vec![
+            "ufw allow 443/tcp",
+            "ufw allow in on wg0 proto tcp from 10.0.0.0/8 to any port 5432 comment Team database",
+            "ufw delete allow in on wg0 from 10.0.0.0/8 to any port 5432 proto tcp comment Team database",
+        ]
+    );
+}
+
+#[test]
+fn failing_rule_is_reported_with_stderr_and_ufw_stays_disabled() {
+    let sandbox = Sandbox::new();
+    sandbox.stub("ufw", r#"[[ "$*" == "allow 443/tcp" ]] && { echo "ERROR: Bad port" >&2; exit 1; }; exit 0"#);
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "ufw configuration"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "partial");
+    assert!(stderr(&output).contains("`sudo ufw allow 443/tcp` failed with exit status: 1: ERROR: Bad port"));
+    assert!(sandbox.calls_to("ufw").contains(&"ufw allow ssh".to_string()));
+    assert!(!sandbox.calls_to("ufw").contains(&"ufw --force enable".to_string()));
+}
+
+#[test]
+fn rules_added_before_a_failure_are_recorded() {
+    let sandbox = Sandbox::new();
+    sandbox.stub("ufw", r#"[[ "$*" == "allow 443/tcp" ]] && exit 1; exit 0"#);
+    sandbox.run(&["-q", "install", "--select", "ufw configuration"]);
+    stub_configured_ufw(&sandbox);
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https"]);
+
+    // ssh was added by the failed run, so deselecting it removes it again.
+    assert_eq!(rule_calls(&sandbox).last().unwrap(), "ufw delete allow 22/tcp");
+}
+
+#[test]
+fn rules_in_place_stay_recorded_when_a_removal_fails_fast() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https,postgres"]);
+    sandbox.stub("ufw", &format!("[[ $1 == delete ]] && exit 1\n{}", configured_ufw()));
+
+    sandbox.run(&["-q", "install", "--fail-fast", "--select", "ufw configuration", "--firewall-rules", "postgres"]);
+    stub_configured_ufw(&sandbox);
+    sandbox.run_ok(&["-q", "uninstall", "--select", "ufw configuration"]);
+
+    // Both the rule that could not be removed and the selected rule that was
+    // already in place are still recorded, so uninstall removes both.
+    let deleted: Vec<String> =
+        rule_calls(&sandbox).into_iter().filter(|call| call.starts_with("ufw delete")).skip(1).collect();
+    assert_eq!(
+        deleted,
+        vec![
+            "ufw delete allow 443/tcp",
+            "ufw delete allow in on wg0 from 10.0.0.0/8 to any port 5432 proto tcp comment Team database",
+        ]
+    );
+}
+
+#[test]
+fn ssh_session_without_ssh_rule_is_not_locked_out() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox
+        .command(&["-o", "ndjson", "install", "--select", "ufw configuration", "--firewall-rules", "https"])
+        .env("SSH_CONNECTION", "10.0.0.2 50000 10.0.0.1 22")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "conflict");
+    assert_eq!(sandbox.calls_to("ufw"), vec!["ufw status verbose", "ufw show added"]);
+}
+
+#[test]
+fn ssh_session_with_ssh_rule_enables_ufw() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox
+        .command(&["-q", "install", "--select", "ufw configuration"])
+        .env("SSH_CONNECTION", "10.0.0.2 50000 10.0.0.1 22")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(sandbox.calls_to("ufw").last().unwrap(), "ufw --force enable");
+}
+
+/// Switches the fixture catalog to another backend, or to detection with `None`.
+fn use_backend(sandbox: &Sandbox, backend: Option<&str>) {
+    let catalog = sandbox.read_asset("UFW/UFW.toml");
+    let setting = backend.map(|name| format!("backend = \"{}\"", name)).unwrap_or_default();
+    sandbox.write_asset("UFW/UFW.toml", &catalog.replace("backend = \"ufw\"", &setting));
+}
+
+const FIREWALLD: &str = r#"case "$*" in
+  --state) echo running ;;
+  "--permanent --list-services") echo "ssh dhcpv6-client" ;;
+  --get-log-denied) echo off ;;
+esac"#;
+
+/// `firewall-cmd` calls that change the configuration.
+fn firewalld_changes(sandbox: &Sandbox) -> Vec<String> {
+    sandbox
+        .calls_to("firewall-cmd")
+        .into_iter()
+        .filter(|call| !call.contains("--state") && !call.contains("--list-") && !call.contains("--get-"))
+        .collect()
+}
+
+#[test]
+fn firewalld_backend_adds_ports_services_and_rich_rules() {
+    let sandbox = Sandbox::new();
+    use_backend(&sandbox, Some("firewalld"));
+    sandbox.stub("firewall-cmd", FIREWALLD);
+    let catalog = sandbox.read_asset("UFW/UFW.toml");
+    let office = "[[rule]]\nname = \"office-ssh\"\nport = \"ssh\"\nfrom = \"192.168.1.0/24\"\n";
+    sandbox.write_asset("UFW/UFW.toml", &format!("{}\n{}", catalog, office));
+
+    let output = sandbox.run_ok(&["-q", "install", "--select", "ufw configuration"]);
+
+    assert_eq!(
+        firewalld_changes(&sandbox),
+        vec![
+            "firewall-cmd --set-log-denied=unicast",
+            "firewall-cmd --permanent --add-port=443/tcp",
+            "firewall-cmd --permanent --add-rich-rule=rule family=\"ipv4\" source address=\"192.168.1.0/24\" \
+             service name=\"ssh\" accept",
+            "firewall-cmd --reload",
+        ]
+    );
+    assert!(sandbox.calls_to("ufw").is_empty());
+    assert!(stderr(&output).contains("left in place: service dhcpv6-client"));
+}
+
+#[test]
+fn rules_the_backend_cannot_express_fail_the_entry() {
+    let sandbox = Sandbox::new();
+    use_backend(&sandbox, Some("firewalld"));
+    sandbox.stub("firewall-cmd", FIREWALLD);
+
+    let output =
+        sandbox.run(&["-o", "ndjson", "install", "--select", "ufw configuration", "--firewall-rules", "postgres"]);
+
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "conflict");
+    assert!(failed["message"].as_str().unwrap().contains("firewalld binds interfaces to zones"));
+    assert!(firewalld_changes(&sandbox).is_empty());
+}
+
+#[test]
+fn nftables_backend_creates_its_table_and_rules() {
+    let sandbox = Sandbox::new();
+    use_backend(&sandbox, Some("nftables"));
+    sandbox.stub("nft", r#"[[ "$1" == -a ]] && exit 1; exit 0"#);
+    sandbox.stub("sh", "");
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https,ssh,postgres"]);
+
+    let calls = sandbox.calls_to("nft");
+    assert_eq!(calls[0], "nft -a list table inet hyde_ext");
+    assert_eq!(calls[1], "nft add table inet hyde_ext");
+    assert_eq!(calls[2], "nft add chain inet hyde_ext input { type filter hook input priority 0; policy drop; }");
+    // IPv6 needs neighbor discovery and router advertisements.
+    assert!(calls.contains(&"nft add rule inet hyde_ext input meta l4proto ipv6-icmp accept".to_string()));
+    assert!(calls.contains(&"nft add rule inet hyde_ext input icmp type echo-request accept".to_string()));
+    assert_eq!(
+        calls[9..],
+        [
+            "nft add rule inet hyde_ext input tcp dport 443 accept",
+            "nft add rule inet hyde_ext input tcp dport 22 accept",
+            "nft add rule inet hyde_ext input iifname \"wg0\" ip saddr 10.0.0.0/8 tcp dport 5432 accept \
+             comment \"Team database\"",
+        ]
+    );
+    // The table is saved for nftables.service, which is enabled to load it at boot.
+    let persist = sandbox.calls_to("sh");
+    assert_eq!(persist.len(), 2);
+    assert!(persist.iter().all(|call| call.contains("nft list table inet hyde_ext; } > /etc/nftables.d/hyde_ext.nft")));
+    assert!(persist[1].contains(">> /etc/nftables.conf; systemctl enable nftables.service"));
+}
+
+#[test]
+fn nftables_rules_are_compared_with_the_listed_table() {
+    let sandbox = Sandbox::new();
+    use_backend(&sandbox, Some("nftables"));
+    let table = r#"table inet hyde_ext { # handle 5
+	chain input { # handle 1
+		type filter hook input priority filter; policy drop;
+		ct state established,related accept # handle 4
+		iifname "lo" accept # handle 5
+		tcp dport 443 accept # handle 6
+		tcp dport 22 accept comment "Secure shell access" # handle 7
+		udp dport 51820 accept # handle 8
+	}
+}"#;
+    sandbox.stub("nft", &format!("[[ \"$1\" == -a ]] && cat <<'EOF'\n{}\nEOF\nexit 0", table));
+    // nftables.service already loads the saved table at boot.
+    sandbox.stub("systemctl", "");
+    sandbox.stub("sh", "");
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "ufw configuration"]);
+
+    assert_eq!(sandbox.calls_to("nft"), vec!["nft -a list table inet hyde_ext"]);
+    assert!(stderr(&output).contains("left in place: input udp dport 51820 accept"));
+    assert!(sandbox.calls_to("sh").is_empty());
+}
+
+#[test]
+fn running_firewalld_is_detected() {
+    let sandbox = Sandbox::new();
+    use_backend(&sandbox, None);
+    sandbox.stub("firewall-cmd", FIREWALLD);
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration"]);
+
+    assert!(sandbox.calls_to("ufw").is_empty());
+    assert!(firewalld_changes(&sandbox).contains(&"firewall-cmd --permanent --add-port=443/tcp".to_string()));
+}
+
+#[test]
+fn switching_profiles_applies_the_profile_rules() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "firewall", "switch", "HOME"]);
+
+    assert_eq!(
+        rule_calls(&sandbox),
+        vec![
+            "ufw allow 443/tcp",
+            "ufw allow ssh",
+            "ufw allow in on wg0 proto tcp from 10.0.0.0/8 to any port 5432 comment Team database",
+        ]
+    );
+}
+
+#[test]
+fn public_profile_removes_the_other_catalog_rules() {
+    let sandbox = Sandbox::new();
+    stub_configured_ufw(&sandbox);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "firewall", "switch", "public"]);
+
+    assert_eq!(
+        rule_calls(&sandbox),
+        vec![
+            "ufw delete allow 443/tcp",
+            "ufw delete allow 22/tcp",
+            "ufw delete allow in on wg0 from 10.0.0.0/8 to any port 5432 proto tcp comment Team database",
+        ]
+    );
+    assert!(stderr(&output).contains("left in place: allow from 192.168.1.0/24 to any port 8080"));
+    let applied = events(&output).into_iter().find(|event| event["event"] == "applied").unwrap();
+    assert_eq!(applied["command"], "firewall switch");
+}
+
+#[test]
+fn profile_policies_replace_the_default_ones() {
+    let sandbox = Sandbox::new();
+    stub_configured_ufw(&sandbox);
+    let lockdown = "[[profile]]\nname = \"lockdown\"\nrules = [\"ssh\"]\nincoming = \"reject\"\noutgoing = \"deny\"\n";
+    let catalog = sandbox.read_asset("UFW/UFW.toml");
+    sandbox.write_asset("UFW/UFW.toml", &format!("{}\n{}", catalog, lockdown));
+
+    sandbox.run_ok(&["-q", "firewall", "switch", "lockdown"]);
+
+    let policies: Vec<String> =
+        sandbox.calls_to("ufw").into_iter().filter(|call| call.starts_with("ufw default")).collect();
+    assert_eq!(policies, vec!["ufw default reject incoming", "ufw default deny outgoing"]);
+}
+
+#[test]
+fn unknown_profile_lists_the_available_ones() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "firewall", "switch", "cafe"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+    assert!(failed["message"].as_str().unwrap().contains("Available profiles: home, public"));
+    assert!(rule_calls(&sandbox).is_empty());
+}
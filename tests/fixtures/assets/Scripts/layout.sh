@@ -0,0 +1,4 @@
+#!/bin/bash
+bluetoothctl info
+hyprctl keyword input:kb_layout us
+echo "layout applied"
@@ -0,0 +1,546 @@
+mod common;
+
+use common::{events, stderr, stdout, Sandbox};
+
+const CUSTOM_MARKER: &str = "# ================== Customized Configurations Below ===========================";
+
+#[test]
+fn config_is_appended_to_target() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/hypr/userprefs.conf", "source = ~/.config/hypr/base.conf\n");
+
+    sandbox.run_ok(&["-q", "install", "--select", "user-preferences.conf"]);
+
+    let expected = format!(
+        "source = ~/.config/hypr/base.conf\n\n{}\n",
+        sandbox.read_asset("Configs/userprefs.conf")
+    );
+    assert_eq!(sandbox.read_home(".config/hypr/userprefs.conf"), expected);
+}
+
+#[test]
+fn config_with_customizations_is_skipped() {
+    let sandbox = Sandbox::new();
+    let original = format!("base\n{}\ncustom = true\n", CUSTOM_MARKER);
+    sandbox.write_home(".config/hypr/userprefs.conf", &original);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "User-Preferences.conf [CONFIG]"]);
+
+    assert_eq!(sandbox.read_home(".config/hypr/userprefs.conf"), original);
+    let events = events(&output);
+    assert_eq!(events[1]["event"], "skipped");
+    assert_eq!(events[1]["entry"], "User-Preferences.conf [CONFIG]");
+}
+
+#[test]
+fn force_appends_config_despite_customizations() {
+    let sandbox = Sandbox::new();
+    let original = format!("base\n{}\n", CUSTOM_MARKER);
+    sandbox.write_home(".config/hypr/userprefs.conf", &original);
+
+    sandbox.run_ok(&["-q", "--force", "install", "--select", "user-preferences.conf"]);
+
+    let contents = sandbox.read_home(".config/hypr/userprefs.conf");
+    assert!(contents.starts_with(&original));
+    assert!(contents.ends_with(&format!("{}\n", sandbox.read_asset("Configs/userprefs.conf"))));
+}
+
+#[test]
+fn zshrc_install_disables_pokemon_colorscripts() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".zshrc", "pokemon-colorscripts --no-title -r\nexport EDITOR=nvim\n");
+
+    sandbox.run_ok(&["-q", "install", "--select", ".zshrc"]);
+
+    let contents = sandbox.read_home(".zshrc");
+    assert!(contents.starts_with("#pokemon-colorscripts --no-title -r\nexport EDITOR=nvim\n"));
+    assert!(contents.ends_with(&format!("{}\n", sandbox.read_asset("Configs/.zshrc"))));
+}
+
+#[test]
+fn fastfetch_images_are_copied() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset("FastFetchAssets/pngs/anime/three.png", "third image");
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/one.png"), "first image");
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/two.png"), "second image");
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/anime/three.png"), "third image");
+}
+
+#[test]
+fn unchanged_images_are_not_copied_again() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "fastfetch images"]);
+
+    let skipped = events(&output).into_iter().find(|event| event["event"] == "skipped").unwrap();
+    assert!(skipped["message"].as_str().unwrap().ends_with("pngs is up to date."));
+}
+
+#[test]
+fn images_changed_by_the_user_are_only_replaced_with_force() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/fastfetch/pngs/one.png", "my image");
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/one.png"), "my image");
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/two.png"), "second image");
+
+    sandbox.run_ok(&["-q", "--force", "install", "--select", "fastfetch images"]);
+
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/one.png"), "first image");
+}
+
+#[test]
+fn images_untouched_since_install_are_updated() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+    sandbox.write_asset("FastFetchAssets/pngs/one.png", "new first image");
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/one.png"), "new first image");
+}
+
+#[test]
+fn packages_of_all_groups_are_installed_in_one_transaction() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm p7zip unzip ttf-fira-code"]
+    );
+}
+
+#[test]
+fn listed_packages_from_official_repositories_are_installed_with_pacman() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"
+if [[ "$1" == -Si ]]; then
+  for package in "${@:2}"; do
+    repository=extra; [[ "$package" == ttf-fira-code ]] && repository=aur
+    printf 'Repository      : %s\nName            : %s\n\n' "$repository" "$package"
+  done
+  exit 0
+fi"#);
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+
+    assert_eq!(sandbox.installs("sudo"), vec!["sudo pacman -S --needed --noconfirm p7zip unzip"]);
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm ttf-fira-code"]
+    );
+}
+
+#[test]
+fn failed_package_transaction_does_not_stop_remaining_entries() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"[[ "$*" == *--needed*p7zip* ]] && exit 1"#);
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "packages", "--select", "ufw configuration"]);
+
+    assert_eq!(output.status.code(), Some(2), "stderr: {}", stderr(&output));
+    assert_eq!(sandbox.installs("yay").len(), 1);
+    assert!(!sandbox.calls_to("ufw").is_empty());
+
+    let events = events(&output);
+    let failed = events.iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["entry"], "Packages [PACKAGES]");
+    assert_eq!(failed["error_kind"], "partial");
+    assert_eq!(events.last().unwrap()["event"], "summary");
+    assert_eq!(events.last().unwrap()["failed"], 1);
+    assert_eq!(events.last().unwrap()["applied"], 1);
+}
+
+#[test]
+fn fail_fast_skips_remaining_entries() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"[[ "$*" == *--needed* ]] && exit 1"#);
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--fail-fast", "--select", "packages", "--select", "ufw configuration"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(sandbox.installs("yay").len(), 1);
+    assert!(sandbox.calls_to("ufw").is_empty());
+    let skipped = events(&output).into_iter().find(|e| e["event"] == "skipped").unwrap();
+    assert_eq!(skipped["entry"], "UFW Configuration [UFW]");
+}
+
+#[test]
+fn ufw_commands_are_issued_through_sudo() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration"]);
+
+    let sudo = sandbox.calls_to("sudo");
+    assert_eq!(sudo[0], "sudo -v");
+    assert_eq!(sudo[1], "sudo ufw status verbose");
+    assert_eq!(sudo[2], "sudo ufw show added");
+    assert_eq!(sudo[3], "sudo ufw default deny incoming");
+    assert_eq!(sudo[4], "sudo ufw default allow outgoing");
+    assert_eq!(sudo[5], "sudo ufw default deny routed");
+    assert_eq!(sudo[6], "sudo ufw logging on medium");
+    assert!(sudo.contains(&"sudo ufw allow 443/tcp".to_string()));
+    assert!(sudo.contains(&"sudo ufw allow ssh".to_string()));
+    assert_eq!(sudo.last().unwrap(), "sudo ufw --force enable");
+    assert_eq!(sandbox.calls_to("ufw").len(), sudo.len() - 1);
+}
+
+#[test]
+fn root_entries_authenticate_once_up_front() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run_ok(&["install", "--select", "ufw configuration", "--select", "packages"]);
+
+    assert_eq!(sandbox.calls()[0], "sudo -v");
+    assert_eq!(sandbox.calls_to("sudo").iter().filter(|call| *call == "sudo -v").count(), 1);
+    assert!(stdout(&output).contains("Needs root (sudo): UFW Configuration [UFW], Packages [PACKAGES]"));
+}
+
+#[test]
+fn entries_without_root_do_not_authenticate() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/hypr/userprefs.conf", "");
+
+    sandbox.run_ok(&["-q", "install", "--select", "user-preferences.conf"]);
+
+    assert!(sandbox.calls_to("sudo").is_empty());
+}
+
+#[test]
+fn failed_authentication_fails_only_root_entries() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/hypr/userprefs.conf", "");
+    sandbox.stub("sudo", r#"[[ "$1" == -v ]] && exit 1; exec "$@""#);
+
+    let output = sandbox.run(&[
+        "-o",
+        "ndjson",
+        "install",
+        "--select",
+        "ufw configuration",
+        "--select",
+        "user-preferences.conf",
+    ]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let events = events(&output);
+    let failed = events.iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["entry"], "UFW Configuration [UFW]");
+    assert!(failed["message"].as_str().unwrap().contains("`sudo -v` failed"));
+    assert!(sandbox.calls_to("ufw").is_empty());
+    assert!(events.iter().any(|event| event["event"] == "applied" && event["handler"] == "configs"));
+}
+
+#[test]
+fn doas_replaces_sudo_for_every_root_command() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "--elevate", "doas", "install", "--select", "ufw configuration", "--select", "packages"]);
+
+    assert!(sandbox.calls_to("sudo").is_empty());
+    let doas = sandbox.calls_to("doas");
+    assert_eq!(doas[0], "doas true");
+    assert!(doas.contains(&"doas ufw --force enable".to_string()));
+    let yay = sandbox.calls_to("yay");
+    assert!(yay.iter().any(|call| call.starts_with("yay --sudo doas ") && call.contains("-S --needed")));
+}
+
+#[test]
+fn script_is_copied_and_started() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run_ok(&["-q", "install", "--select", "layout automation script"]);
+
+    assert_eq!(
+        sandbox.read_home("scripts/layout.sh"),
+        sandbox.read_asset("Scripts/layout.sh")
+    );
+    assert!(sandbox.calls().contains(&"bluetoothctl info".to_string()));
+    assert!(sandbox.calls().contains(&"hyprctl keyword input:kb_layout us".to_string()));
+    assert!(common::stdout(&output).contains("layout applied"));
+}
+
+#[test]
+fn unknown_selection_is_rejected() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-q", "install", "--select", "does-not-exist"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("No entry matches 'does-not-exist'"));
+    assert!(sandbox.calls().is_empty());
+}
+
+#[test]
+fn unknown_selection_is_reported_with_a_summary() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "does-not-exist"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let events = events(&output);
+    let failed = events.iter().find(|event| event["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+    let summary = events.last().unwrap();
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["exit_code"], 1);
+}
+
+#[test]
+fn package_groups_can_be_selected() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages", "--groups", "fonts"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm ttf-fira-code"]
+    );
+}
+
+#[test]
+fn suggested_packages_are_opt_in() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages", "--packages", "noto-fonts-emoji,unzip"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm unzip noto-fonts-emoji"]
+    );
+}
+
+#[test]
+fn unknown_package_group_is_rejected() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "packages", "--groups", "games"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(sandbox.installs("yay").is_empty());
+    let failed = events(&output).into_iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "selection");
+}
+
+const MANIFEST: &str = r#"
+[[group]]
+name = "Drivers"
+
+[[group.package]]
+name = "opencl-amd"
+source = "aur"
+when = { gpu = "amd" }
+post_install = ["touch \"$HOME/opencl-hook\""]
+
+[[group.package]]
+name = "mesa"
+source = "repo"
+
+[[group.package]]
+name = "vulkan-tools"
+optional = true
+"#;
+
+fn use_manifest(sandbox: &Sandbox) {
+    sandbox.write_asset("Packages/packages.toml", MANIFEST);
+    let catalog = sandbox.read_asset("Packages/Packages.toml").replace("packages.list", "packages.toml");
+    sandbox.write_asset("Packages/Packages.toml", &catalog);
+}
+
+#[test]
+fn manifest_conditions_and_hooks_are_applied() {
+    let sandbox = Sandbox::new();
+    use_manifest(&sandbox);
+    sandbox.stub("lspci", r#"echo "03:00.0 VGA compatible controller: Advanced Micro Devices, Inc. [AMD/ATI] Navi 23""#);
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+
+    assert_eq!(sandbox.installs("sudo"), vec!["sudo pacman -S --needed --noconfirm mesa"]);
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm opencl-amd"]
+    );
+    assert!(sandbox.home_path("opencl-hook").exists());
+}
+
+#[test]
+fn manifest_packages_with_unmet_conditions_are_skipped() {
+    let sandbox = Sandbox::new();
+    use_manifest(&sandbox);
+    sandbox.stub("lspci", r#"echo "00:02.0 VGA compatible controller: Intel Corporation UHD Graphics 620""#);
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages", "--packages", "mesa,vulkan-tools"]);
+
+    assert_eq!(sandbox.installs("sudo"), vec!["sudo pacman -S --needed --noconfirm mesa"]);
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm vulkan-tools"]
+    );
+    assert!(!sandbox.home_path("opencl-hook").exists());
+}
+
+#[test]
+fn invalid_manifest_is_reported() {
+    let sandbox = Sandbox::new();
+    use_manifest(&sandbox);
+    sandbox.write_asset("Packages/packages.toml", "[[group]]\nname = \"Broken\"\nunknown = 1\n");
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--select", "packages"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "parse");
+}
+
+#[test]
+fn detected_aur_helper_gets_its_own_flags() {
+    let sandbox = Sandbox::new();
+    std::fs::remove_file(sandbox.bin.join("yay")).unwrap();
+    sandbox.stub_package_manager("paru", "");
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages", "--groups", "fonts"]);
+
+    assert_eq!(sandbox.installs("paru"), vec!["paru --skipreview -S --needed --noconfirm ttf-fira-code"]);
+}
+
+#[test]
+fn pacman_only_backend_refuses_aur_packages() {
+    let sandbox = Sandbox::new();
+    use_manifest(&sandbox);
+    sandbox.stub("lspci", r#"echo "03:00.0 VGA compatible controller: AMD/ATI Navi 23""#);
+
+    let output = sandbox.run(&["-o", "ndjson", "install", "--package-manager", "pacman", "--select", "packages"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(sandbox.installs("sudo"), vec!["sudo pacman -S --needed --noconfirm mesa"]);
+    assert!(sandbox.installs("yay").is_empty());
+    let failed = events(&output).into_iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "partial");
+}
+
+#[test]
+fn packages_are_resolved_before_installing() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", r#"
+[[ "$1" == -Qq ]] && { printf 'unzip\nnoto-fonts\n'; exit 0; }
+[[ "$*" == "-Sp --print-format %n p7zip" ]] && { echo 7zip; exit 0; }
+[[ "$1" == -Sp ]] && exit 1"#);
+    sandbox.stub_package_manager("yay", r#"
+if [[ "$1" == -Si ]]; then
+  printf 'Name            : unzip\nConflicts With  : None\n\n'
+  printf 'Name            : ttf-fira-code\nConflicts With  : noto-fonts>=2\n\n'
+  echo "error: package 'p7zip' was not found" >&2
+  exit 1
+fi"#);
+
+    let output = sandbox.run_ok(&["install", "--select", "packages"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm 7zip"]
+    );
+    let stdout = common::stdout(&output);
+    assert!(stdout.contains("p7zip was renamed to 7zip"), "{}", stdout);
+    assert!(stdout.contains("unzip: already installed"), "{}", stdout);
+    assert!(stdout.contains("ttf-fira-code: conflicts with noto-fonts"), "{}", stdout);
+}
+
+#[test]
+fn first_of_two_conflicting_packages_is_installed() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"
+if [[ "$1" == -Si ]]; then
+  printf 'Name            : p7zip\nConflicts With  : None\n\n'
+  printf 'Name            : unzip\nConflicts With  : ttf-fira-code\n\n'
+  printf 'Name            : ttf-fira-code\nConflicts With  : unzip\n\n'
+  exit 0
+fi"#);
+
+    let output = sandbox.run_ok(&["install", "--select", "packages"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm p7zip unzip"]
+    );
+    let stdout = common::stdout(&output);
+    assert!(stdout.contains("ttf-fira-code: conflicts with unzip"), "{}", stdout);
+}
+
+#[test]
+fn unavailable_packages_skip_the_entry() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"[[ "$1" == -Si ]] && exit 1"#);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "packages"]);
+
+    assert!(sandbox.installs("yay").is_empty());
+    let skipped = events(&output).into_iter().find(|e| e["event"] == "skipped").unwrap();
+    assert_eq!(skipped["entry"], "Packages [PACKAGES]");
+    assert!(stderr(&output).contains("p7zip: not found"));
+}
+
+#[test]
+fn installed_flatpaks_are_skipped() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset("Packages/packages.list", "# ===== Apps =====\nflatpak:com.obsproject.Studio\n#cargo:zellij\n");
+    sandbox.set_installed_packages(&["flatpak:com.obsproject.Studio"]);
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages", "--packages", "cargo:zellij"]);
+
+    assert!(!sandbox.calls_to("flatpak").iter().any(|call| call.starts_with("flatpak install")));
+    assert!(sandbox.calls_to("cargo").contains(&"cargo install zellij".to_string()));
+}
+
+#[test]
+fn helper_output_is_shown_as_progress_and_logged() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("yay", r#"
+if [[ "$*" == *--needed* ]]; then
+  echo "resolving dependencies..."
+  echo " p7zip-17.05-2-x86_64 downloading..."
+  echo "(1/3) installing p7zip"
+  echo "(2/3) installing unzip"
+  echo "warning: ttf-fira-code-6.2-2 is up to date" >&2
+fi"#);
+
+    let output = sandbox.run_ok(&["install", "--select", "packages"]);
+
+    let stdout = common::stdout(&output);
+    assert!(stdout.contains("-> downloading p7zip-17.05-2-x86_64"), "{}", stdout);
+    assert!(stdout.contains("-> [2/3] installing unzip"), "{}", stdout);
+    assert!(!stdout.contains("resolving dependencies"));
+
+    let logs = sandbox.home_path(".local/state/hyde-ext/logs");
+    let log = std::fs::read_dir(logs).unwrap().next().unwrap().unwrap().path();
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.contains("[OUTPUT] resolving dependencies..."));
+    assert!(log.contains("[OUTPUT] warning: ttf-fira-code-6.2-2 is up to date"));
+}
+
+#[test]
+fn only_the_newest_logs_are_kept() {
+    let sandbox = Sandbox::new();
+    for second in 0..25 {
+        sandbox.write_home(&format!(".local/state/hyde-ext/logs/2000-01-01_0000{:02}-1.log", second), "old run");
+    }
+
+    sandbox.run_ok(&["-q", "status"]);
+
+    let logs = sandbox.home_path(".local/state/hyde-ext/logs");
+    let mut names: Vec<String> = std::fs::read_dir(logs)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 20);
+    assert_eq!(names[0], "2000-01-01_000006-1.log");
+    assert!(!names[19].starts_with("2000-"));
+}
@@ -0,0 +1,239 @@
+mod common;
+
+use common::{events, stdout, Sandbox};
+
+const SYSTEM: &str = r#"
+case "$1" in
+  -Qqe) printf 'base\nhyprland\nkitty\nnmap\nttf-fira-code\nyay\n'; exit 0 ;;
+  -Qqm) printf 'yay\n'; exit 0 ;;
+  -Si)
+    for package in "${@:2}"; do
+      case "$package" in
+        base) repository=core ;;
+        *) repository=extra ;;
+      esac
+      printf 'Repository      : %s\nName            : %s\n\n' "$repository" "$package"
+    done
+    exit 0 ;;
+esac"#;
+
+#[test]
+fn export_groups_packages_by_repository() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", SYSTEM);
+    sandbox.write_home("HyDE/Scripts/pkg_core.lst", "# --- core ---\nhyprland\nkitty|hyprland\n");
+
+    sandbox.run_ok(&["-q", "packages", "export", "exported/packages.list"]);
+
+    assert_eq!(
+        std::fs::read_to_string(sandbox.root.join("exported/packages.list")).unwrap(),
+        "# ========= Core =========\nbase\n\n\
+         # ========= Extra =========\nnmap\nttf-fira-code\n\n\
+         # ========= AUR =========\nyay\n"
+    );
+}
+
+#[test]
+fn export_applies_rules_and_base_lists() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", SYSTEM);
+    let base = sandbox.write_home("base.lst", "base\n");
+    let rules = sandbox.write_home("rules.toml", "[[rule]]\ngroup = \"Fonts\"\npattern = \"^ttf-\"\n");
+
+    let output = sandbox.run_ok(&[
+        "packages",
+        "export",
+        "--base",
+        base.to_str().unwrap(),
+        "--rules",
+        rules.to_str().unwrap(),
+    ]);
+
+    assert_eq!(
+        stdout(&output),
+        "# ========= Fonts =========\nttf-fira-code\n\n\
+         # ========= Extra =========\nhyprland\nkitty\nnmap\n\n\
+         # ========= AUR =========\nyay\n"
+    );
+}
+
+#[test]
+fn exported_list_is_installable() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", SYSTEM);
+    sandbox.write_home("HyDE/Scripts/pkg_core.lst", "base\nhyprland\nkitty\n");
+    let list = sandbox.assets.join("Packages/packages.list");
+    std::fs::remove_file(&list).unwrap();
+
+    sandbox.run_ok(&["-q", "packages", "export", list.to_str().unwrap()]);
+    sandbox.stub_package_manager("pacman", "");
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+
+    assert_eq!(
+        sandbox.installs("yay"),
+        vec!["yay --answerclean None --answerdiff None -S --needed --noconfirm nmap ttf-fira-code yay"]
+    );
+}
+
+#[test]
+fn export_does_not_overwrite_without_force() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", SYSTEM);
+    sandbox.write_home("packages.list", "mine\n");
+    let file = sandbox.home_path("packages.list");
+
+    let output = sandbox.run(&["-o", "ndjson", "packages", "export", file.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(events(&output)[1]["error_kind"], "conflict");
+    assert_eq!(sandbox.read_home("packages.list"), "mine\n");
+}
+
+const DRIFTED: &str = r#"
+case "$1" in
+  -Q) printf 'hyprland 0.45-1\nkitty 0.36-1\nnmap 7.94-1\nttf-fira-code 6.2-2\nunzip 6.0-21\n'; exit 0 ;;
+  -Qqe) printf 'hyprland\nkitty\nnmap\nttf-fira-code\nunzip\n'; exit 0 ;;
+esac"#;
+
+#[test]
+fn diff_reports_missing_extra_and_mismatched_packages() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", DRIFTED);
+    sandbox.set_installed_packages(&["hyprland", "kitty", "nmap", "ttf-fira-code", "unzip"]);
+    let base = sandbox.write_home("base.lst", "hyprland\n");
+    let list = sandbox.write_home(
+        "team.list",
+        "# ===== Tools =====\np7zip\nunzip 6.0\nnmap 7.95-1\n\n# ===== Fonts =====\nttf-fira-code 6.2-2\n#noto-fonts-emoji\n",
+    );
+
+    let output = sandbox.run_ok(&[
+        "-o",
+        "ndjson",
+        "packages",
+        "diff",
+        "--against",
+        list.to_str().unwrap(),
+        "--base",
+        base.to_str().unwrap(),
+    ]);
+
+    let statuses: Vec<(String, bool, String)> = events(&output)
+        .iter()
+        .filter(|event| event["event"] == "status")
+        .map(|event| {
+            (
+                event["entry"].as_str().unwrap().to_string(),
+                event["installed"].as_bool().unwrap(),
+                event["message"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            (
+                "Tools".to_string(),
+                false,
+                "missing: p7zip; version: nmap 7.94-1 installed, 7.95-1 listed".to_string()
+            ),
+            ("Fonts".to_string(), true, "in sync".to_string()),
+            ("Not in list (Extra)".to_string(), false, "extra: kitty".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn diff_ignores_inline_comments() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", DRIFTED);
+    sandbox.set_installed_packages(&["hyprland", "kitty", "nmap", "ttf-fira-code", "unzip"]);
+    let base = sandbox.write_home("base.lst", "hyprland\nkitty\n");
+    let list = sandbox.write_home(
+        "team.list",
+        "# ===== Tools =====\nunzip # archive tool\nnmap 7.94-1 # pinned\nttf-fira-code latest\n",
+    );
+
+    let output = sandbox.run_ok(&[
+        "-o",
+        "ndjson",
+        "packages",
+        "diff",
+        "--against",
+        list.to_str().unwrap(),
+        "--base",
+        base.to_str().unwrap(),
+    ]);
+
+    let status = events(&output).into_iter().find(|event| event["event"] == "status").unwrap();
+    assert_eq!(status["entry"], "Tools");
+    assert_eq!(status["message"], "in sync");
+}
+
+#[test]
+fn diff_compares_only_pacman_versions_and_groups_extra_packages() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", &format!("[[ $1 == -Qqm ]] && {{ echo kitty; exit 0; }}\n{}", DRIFTED));
+    sandbox.set_installed_packages(&["cargo:nmap", "hyprland", "kitty", "nmap", "ttf-fira-code", "unzip"]);
+    let base = sandbox.write_home("base.lst", "");
+    // cargo:nmap shares its name with the pacman package, installed as 7.94-1.
+    let list = sandbox.write_home("team.list", "# ===== Tools =====\ncargo:nmap 0.3.0\nunzip\n");
+
+    let output = sandbox.run_ok(&[
+        "-o",
+        "ndjson",
+        "packages",
+        "diff",
+        "--against",
+        list.to_str().unwrap(),
+        "--base",
+        base.to_str().unwrap(),
+    ]);
+
+    let statuses: Vec<(String, String)> = events(&output)
+        .iter()
+        .filter(|event| event["event"] == "status")
+        .map(|event| (event["entry"].as_str().unwrap().to_string(), event["message"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("Tools".to_string(), "in sync".to_string()),
+            ("Not in list (Extra)".to_string(), "extra: hyprland, nmap, ttf-fira-code".to_string()),
+            ("Not in list (AUR)".to_string(), "extra: kitty".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn diff_defaults_to_the_catalog_list() {
+    let sandbox = Sandbox::new();
+    sandbox.set_installed_packages(&["p7zip", "unzip", "ttf-fira-code"]);
+
+    let output = sandbox.run_ok(&["-q", "packages", "diff"]);
+
+    assert!(stdout(&output).contains("installed packages match the list"), "{}", stdout(&output));
+}
+
+#[test]
+fn export_writes_versions() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", &format!("[[ $1 == -Q ]] && {{ printf 'nmap 7.95-1\\n'; exit 0; }}\n{}", SYSTEM));
+    sandbox.write_home("HyDE/Scripts/pkg_core.lst", "base\nhyprland\nkitty\nttf-fira-code\nyay\n");
+
+    let output = sandbox.run_ok(&["packages", "export", "--versions"]);
+
+    assert_eq!(stdout(&output), "# ========= Extra =========\nnmap 7.95-1\n");
+}
+
+#[test]
+fn export_to_stdout_puts_the_list_in_the_json_report() {
+    let sandbox = Sandbox::new();
+    sandbox.stub_package_manager("pacman", SYSTEM);
+    sandbox.write_home("HyDE/Scripts/pkg_core.lst", "base\nhyprland\nkitty\nttf-fira-code\nyay\n");
+
+    let output = sandbox.run_ok(&["-o", "json", "packages", "export"]);
+
+    let report: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let applied = report["events"].as_array().unwrap().iter().find(|event| event["event"] == "applied").unwrap();
+    assert_eq!(applied["output"], "# ========= Extra =========\nnmap\n");
+}
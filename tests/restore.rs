@@ -0,0 +1,78 @@
+mod common;
+
+use common::{Sandbox, stdout};
+
+const BACKUP: &str = ".config/cfg_backups/240512_1200";
+
+fn customized(base: &str, custom: &str) -> String {
+    format!(
+        "{}\n# ==============================================================================\n\
+         # ================== Customized Configurations Below ===========================\n\
+         # ==============================================================================\n{}",
+        base, custom
+    )
+}
+
+#[test]
+fn custom_configurations_are_restored_from_backup() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(
+        &format!("{}/.config/hypr/userprefs.conf", BACKUP),
+        &customized("old = 1", "bind = ALT,F12, exec, hyprshot -m region\n"),
+    );
+    sandbox.write_home(".config/hypr/userprefs.conf", "new = 1\n");
+
+    let output = sandbox.run_ok(&["-o", "json", "restore", "--backup", "240512_1200"]);
+
+    let contents = sandbox.read_home(".config/hypr/userprefs.conf");
+    assert!(contents.starts_with("new = 1\n"));
+    assert!(contents.contains("Auto-restored by HyDE-Ext"));
+    assert!(contents.contains("bind = ALT,F12, exec, hyprshot -m region\n"));
+
+    let document: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(document["summary"]["applied"], 1);
+    assert_eq!(document["exit_code"], 0);
+}
+
+#[test]
+fn files_missing_from_current_configuration_are_not_restored() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(
+        &format!("{}/.config/kitty/kitty.conf", BACKUP),
+        &customized("font_size 11", "font_size 13\n"),
+    );
+
+    sandbox.run_ok(&["-q", "restore", "--backup", "240512_1200"]);
+
+    assert!(!sandbox.home_path(".config/kitty/kitty.conf").exists());
+}
+
+#[test]
+fn existing_customizations_require_confirmation() {
+    let sandbox = Sandbox::new();
+    let current = customized("new = 1", "already = restored\n");
+    sandbox.write_home(
+        &format!("{}/.config/hypr/userprefs.conf", BACKUP),
+        &customized("old = 1", "custom = true\n"),
+    );
+    sandbox.write_home(".config/hypr/userprefs.conf", &current);
+
+    sandbox.run_ok(&["-q", "restore", "--backup", "240512_1200"]);
+    assert_eq!(sandbox.read_home(".config/hypr/userprefs.conf"), current);
+
+    sandbox.run_ok(&["-q", "--yes", "restore", "--backup", "240512_1200"]);
+    assert!(sandbox.read_home(".config/hypr/userprefs.conf").ends_with("custom = true\n\n"));
+}
+
+#[test]
+fn unknown_backup_fails() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(&format!("{}/.config/hypr/userprefs.conf", BACKUP), "x\n");
+
+    let output = sandbox.run(&["-o", "ndjson", "restore", "--backup", "missing"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let events = common::events(&output);
+    assert_eq!(events[0]["event"], "failed");
+    assert_eq!(events[0]["error_kind"], "selection");
+}
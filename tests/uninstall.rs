@@ -0,0 +1,309 @@
+mod common;
+
+use common::{events, stdout, Sandbox};
+
+#[test]
+fn uninstall_removes_appended_configuration() {
+    let sandbox = Sandbox::new();
+    let original = "source = ~/.config/hypr/base.conf\n";
+    sandbox.write_home(".config/hypr/userprefs.conf", original);
+
+    sandbox.run_ok(&["-q", "install", "--select", "user-preferences.conf"]);
+    assert_ne!(sandbox.read_home(".config/hypr/userprefs.conf"), original);
+
+    sandbox.run_ok(&["-q", "uninstall", "--all"]);
+    assert_eq!(sandbox.read_home(".config/hypr/userprefs.conf"), original);
+}
+
+#[test]
+fn uninstall_enables_pokemon_colorscripts_again() {
+    let sandbox = Sandbox::new();
+    let zshrc = "pokemon-colorscripts --no-title -r\nexport EDITOR=nvim\n";
+    sandbox.write_home(".zshrc", zshrc);
+    sandbox.run_ok(&["-q", "install", "--select", ".zshrc"]);
+    assert!(sandbox.read_home(".zshrc").starts_with("#pokemon-colorscripts"));
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", ".zshrc"]);
+
+    assert_eq!(sandbox.read_home(".zshrc"), zshrc);
+}
+
+#[test]
+fn uninstall_removes_copied_files() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/fastfetch/pngs/mine.png", "user image");
+    sandbox.write_asset("FastFetchAssets/pngs/anime/three.png", "third image");
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+    sandbox.run_ok(&["-q", "uninstall", "--select", "fastfetch images"]);
+
+    assert!(!sandbox.home_path(".config/fastfetch/pngs/anime/three.png").exists());
+    assert!(!sandbox.home_path(".config/fastfetch/pngs/one.png").exists());
+    assert!(!sandbox.home_path(".config/fastfetch/pngs/two.png").exists());
+    assert_eq!(sandbox.read_home(".config/fastfetch/pngs/mine.png"), "user image");
+}
+
+#[test]
+fn files_no_longer_shipped_stay_recorded() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+    std::fs::remove_file(sandbox.assets.join("FastFetchAssets/pngs/two.png")).unwrap();
+    sandbox.write_asset("FastFetchAssets/pngs/one.png", "new first image");
+
+    sandbox.run_ok(&["-q", "install", "--select", "fastfetch images"]);
+    sandbox.run_ok(&["-q", "uninstall", "--select", "fastfetch images"]);
+
+    assert!(!sandbox.home_path(".config/fastfetch/pngs/one.png").exists());
+    assert!(!sandbox.home_path(".config/fastfetch/pngs/two.png").exists());
+}
+
+#[test]
+fn modified_configuration_is_reported_as_conflict() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/hypr/userprefs.conf", "base\n");
+    sandbox.run_ok(&["-q", "install", "--select", "user-preferences.conf"]);
+    sandbox.write_home(".config/hypr/userprefs.conf", "rewritten by the user\n");
+
+    let output = sandbox.run(&["-o", "ndjson", "uninstall", "--all"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let failed = events(&output).into_iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "conflict");
+    assert_eq!(sandbox.read_home(".config/hypr/userprefs.conf"), "rewritten by the user\n");
+}
+
+#[test]
+fn status_follows_install_and_uninstall() {
+    let sandbox = Sandbox::new();
+    sandbox.write_home(".config/hypr/userprefs.conf", "base\n");
+
+    let installed = |sandbox: &Sandbox| {
+        let output = sandbox.run_ok(&["-o", "ndjson", "status"]);
+        events(&output)
+            .into_iter()
+            .find(|e| e["entry"] == "User-Preferences.conf [CONFIG]")
+            .unwrap()["installed"]
+            .clone()
+    };
+
+    assert_eq!(installed(&sandbox), false);
+    sandbox.run_ok(&["-q", "install", "--select", "user-preferences.conf"]);
+    assert_eq!(installed(&sandbox), true);
+    sandbox.run_ok(&["-q", "uninstall", "--all"]);
+    assert_eq!(installed(&sandbox), false);
+}
+
+#[test]
+fn uninstall_removes_only_packages_added_by_hyde_ext() {
+    let sandbox = Sandbox::new();
+    sandbox.set_installed_packages(&["unzip"]);
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+    assert_eq!(sandbox.installed_packages(), vec!["unzip", "p7zip", "ttf-fira-code"]);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "status"]);
+    let status = events(&output).into_iter().find(|e| e["entry"] == "Packages [PACKAGES]").unwrap();
+    assert_eq!(status["message"], "installed, 2 of 2 packages present");
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+    assert_eq!(sandbox.calls_to("sudo").last().unwrap(), "sudo pacman -Rns p7zip ttf-fira-code");
+    assert_eq!(sandbox.installed_packages(), vec!["unzip"]);
+}
+
+#[test]
+fn status_lists_packages_removed_outside_hyde_ext() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+    sandbox.set_installed_packages(&["p7zip", "unzip"]);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "status"]);
+
+    let status = events(&output).into_iter().find(|e| e["entry"] == "Packages [PACKAGES]").unwrap();
+    assert_eq!(status["message"], "installed, 2 of 3 packages present (missing: ttf-fira-code)");
+}
+
+#[test]
+fn flatpak_and_cargo_packages_are_tracked() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset(
+        "Packages/packages.list",
+        "# ===== Apps =====\nflatpak:com.obsproject.Studio\ncargo:ripgrep\nvlc\n",
+    );
+
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+    assert_eq!(
+        sandbox.calls_to("flatpak")[1],
+        "flatpak install --user --noninteractive flathub com.obsproject.Studio"
+    );
+    assert!(sandbox.calls_to("cargo").contains(&"cargo install ripgrep".to_string()));
+    assert_eq!(sandbox.installed_packages(), vec!["vlc", "flatpak:com.obsproject.Studio", "cargo:ripgrep"]);
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+    assert!(sandbox.calls_to("flatpak").contains(&"flatpak uninstall --user --noninteractive com.obsproject.Studio".to_string()));
+    assert!(sandbox.calls_to("cargo").contains(&"cargo uninstall ripgrep".to_string()));
+    assert!(sandbox.installed_packages().is_empty());
+}
+
+#[test]
+fn packages_added_before_a_failure_are_uninstalled() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset(
+        "Packages/packages.list",
+        "# ===== Tools =====\nvlc\n\n# ===== Apps =====\nflatpak:com.obsproject.Studio\n",
+    );
+    sandbox.stub("flatpak", r#"[[ "$1" == install ]] && exit 1; exit 0"#);
+
+    let output = sandbox.run(&["-q", "install", "--select", "packages"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(sandbox.installed_packages(), vec!["vlc"]);
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+    assert_eq!(sandbox.calls_to("sudo").last().unwrap(), "sudo pacman -Rns vlc");
+    assert!(sandbox.installed_packages().is_empty());
+}
+
+const SERVICES: &str = r#"
+[[group]]
+name = "Containers"
+
+[[group.package]]
+name = "docker"
+services = ["docker.service"]
+user_services = ["podman.socket"]
+user_groups = ["docker", "wheel"]
+
+[[group.package]]
+name = "ufw"
+services = ["ufw.service"]
+"#;
+
+#[test]
+fn services_and_groups_are_set_up_and_reverted() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset("Packages/packages.toml", SERVICES);
+    let catalog = sandbox.read_asset("Packages/Packages.toml").replace("packages.list", "packages.toml");
+    sandbox.write_asset("Packages/Packages.toml", &catalog);
+    sandbox.stub("systemctl", r#"[[ "$*" == "is-enabled --quiet ufw.service" ]] && exit 0; [[ "$*" == *is-enabled* ]] && exit 1; exit 0"#);
+
+    let output = sandbox.run_ok(&["install", "--select", "packages"]);
+
+    let sudo = sandbox.calls_to("sudo");
+    assert!(sudo.contains(&"sudo systemctl enable --now docker.service".to_string()));
+    assert!(sudo.contains(&"sudo usermod -aG docker tester".to_string()));
+    assert!(!sudo.iter().any(|call| call.contains("ufw.service") || call.contains("wheel")));
+    assert!(sandbox.calls().contains(&"systemctl --user enable --now podman.socket".to_string()));
+    assert!(common::stderr(&output).contains("Log out and back in for the new group membership (docker)"));
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+
+    let sudo = sandbox.calls_to("sudo");
+    assert!(sudo.contains(&"sudo systemctl disable --now docker.service".to_string()));
+    assert!(sudo.contains(&"sudo gpasswd -d tester docker".to_string()));
+    assert!(!sudo.iter().any(|call| call.contains("disable --now ufw.service")));
+    assert!(sandbox.calls().contains(&"systemctl --user disable --now podman.socket".to_string()));
+}
+
+#[test]
+fn services_and_groups_of_installed_packages_are_set_up() {
+    let sandbox = Sandbox::new();
+    sandbox.set_installed_packages(&["docker", "ufw"]);
+    sandbox.write_asset("Packages/packages.toml", SERVICES);
+    let catalog = sandbox.read_asset("Packages/Packages.toml").replace("packages.list", "packages.toml");
+    sandbox.write_asset("Packages/Packages.toml", &catalog);
+    sandbox.stub("systemctl", r#"[[ "$*" == *is-enabled* ]] && exit 1; exit 0"#);
+
+    let output = sandbox.run_ok(&["-o", "ndjson", "install", "--select", "packages"]);
+
+    assert!(events(&output).iter().any(|event| event["event"] == "applied"));
+    assert!(sandbox.installs("yay").is_empty());
+    let sudo = sandbox.calls_to("sudo");
+    assert!(sudo.contains(&"sudo systemctl enable --now docker.service".to_string()));
+    assert!(sudo.contains(&"sudo systemctl enable --now ufw.service".to_string()));
+    assert!(sudo.contains(&"sudo usermod -aG docker tester".to_string()));
+
+    // The packages were there before, so uninstall only reverts the setup.
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+    assert!(sandbox.calls_to("sudo").contains(&"sudo systemctl disable --now docker.service".to_string()));
+    assert_eq!(sandbox.installed_packages(), vec!["docker", "ufw"]);
+}
+
+#[test]
+fn installed_packages_unknown_to_the_repositories_are_set_up() {
+    let sandbox = Sandbox::new();
+    sandbox.set_installed_packages(&["docker", "ufw"]);
+    sandbox.write_asset("Packages/packages.toml", SERVICES);
+    let catalog = sandbox.read_asset("Packages/Packages.toml").replace("packages.list", "packages.toml");
+    sandbox.write_asset("Packages/Packages.toml", &catalog);
+    sandbox.stub("systemctl", r#"[[ "$*" == *is-enabled* ]] && exit 1; exit 0"#);
+    // docker was built locally, so `-Si` does not find it.
+    sandbox.stub_package_manager("yay", r#"
+if [[ "$1" == -Si ]]; then
+  printf 'Name            : ufw\nConflicts With  : None\n\n'
+  echo "error: package 'docker' was not found" >&2
+  exit 1
+fi"#);
+
+    let output = sandbox.run_ok(&["install", "--select", "packages"]);
+
+    assert!(stdout(&output).contains("docker: already installed"), "{}", stdout(&output));
+    assert!(sandbox.installs("yay").is_empty());
+    let sudo = sandbox.calls_to("sudo");
+    assert!(sudo.contains(&"sudo systemctl enable --now docker.service".to_string()));
+    assert!(sudo.contains(&"sudo usermod -aG docker tester".to_string()));
+}
+
+#[test]
+fn uninstall_removes_only_firewall_rules_added_by_hyde_ext() {
+    let sandbox = Sandbox::new();
+    sandbox.run_ok(&["-q", "install", "--select", "ufw configuration", "--firewall-rules", "https"]);
+    let fixtures = common::fixtures_dir().join("ufw");
+    sandbox.stub(
+        "ufw",
+        &format!(
+            r#"case "$1 $2" in
+  "status verbose") cat '{}' ;;
+  "show added") cat '{}' ;;
+esac"#,
+            fixtures.join("status-verbose.txt").display(),
+            fixtures.join("show-added.txt").display()
+        ),
+    );
+
+    sandbox.run_ok(&["-q", "uninstall", "--select", "ufw configuration"]);
+
+    let deleted: Vec<String> =
+        sandbox.calls_to("ufw").into_iter().filter(|call| call.starts_with("ufw delete")).collect();
+    assert_eq!(deleted, vec!["ufw delete allow 443/tcp"]);
+    let output = sandbox.run_ok(&["-o", "ndjson", "status"]);
+    let status = events(&output).into_iter().find(|e| e["entry"] == "UFW Configuration [UFW]").unwrap();
+    assert_eq!(status["installed"], false);
+}
+
+#[test]
+fn failed_group_removal_does_not_stop_the_uninstall() {
+    let sandbox = Sandbox::new();
+    sandbox.write_asset("Packages/packages.toml", SERVICES);
+    let catalog = sandbox.read_asset("Packages/Packages.toml").replace("packages.list", "packages.toml");
+    sandbox.write_asset("Packages/Packages.toml", &catalog);
+    sandbox.run_ok(&["-q", "install", "--select", "packages"]);
+    sandbox.stub("gpasswd", "exit 1");
+
+    let output = sandbox.run(&["-o", "ndjson", "uninstall", "--select", "packages"]);
+
+    let failed = events(&output).into_iter().find(|e| e["event"] == "failed").unwrap();
+    assert_eq!(failed["error_kind"], "partial");
+    assert_eq!(failed["message"], "Failed: docker");
+    let sudo = sandbox.calls_to("sudo");
+    assert!(sudo.contains(&"sudo systemctl disable --now docker.service".to_string()));
+    assert!(sudo.contains(&"sudo systemctl disable --now ufw.service".to_string()));
+    assert!(sandbox.installed_packages().is_empty());
+
+    // Only the group membership is still recorded.
+    sandbox.stub("gpasswd", "");
+    sandbox.run_ok(&["-q", "uninstall", "--select", "packages"]);
+    let calls = sandbox.calls();
+    let count = |wanted: &str| calls.iter().filter(|call| *call == wanted).count();
+    assert_eq!(count("gpasswd -d tester docker"), 2);
+    assert_eq!(count("systemctl disable --now docker.service"), 1);
+    assert_eq!(count("pacman -Rns docker ufw"), 1);
+}